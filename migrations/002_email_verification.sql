@@ -0,0 +1,4 @@
+ALTER TABLE accounts ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Accounts created before verification existed keep being able to post.
+UPDATE accounts SET email_verified = TRUE;
@@ -0,0 +1,11 @@
+ALTER TABLE accounts ADD COLUMN totp_secret VARCHAR(64);
+ALTER TABLE accounts ADD COLUMN totp_last_step BIGINT;
+
+CREATE TABLE recovery_codes (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX recovery_codes_account_id ON recovery_codes (account_id);
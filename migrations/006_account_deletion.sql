@@ -0,0 +1,2 @@
+ALTER TABLE accounts ADD COLUMN deletion_scheduled_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE accounts ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
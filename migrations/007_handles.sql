@@ -0,0 +1,13 @@
+ALTER TABLE accounts ADD COLUMN handle VARCHAR(32);
+
+-- Existing accounts get a placeholder handle they can rename from the settings page.
+UPDATE accounts SET handle = 'user' || id;
+
+ALTER TABLE accounts ALTER COLUMN handle SET NOT NULL;
+CREATE UNIQUE INDEX accounts_handle_key ON accounts (LOWER(handle));
+
+CREATE TABLE handle_redirects (
+    handle VARCHAR(32) PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
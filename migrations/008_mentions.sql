@@ -0,0 +1,8 @@
+CREATE TABLE mentions (
+    tweet_id INTEGER NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    handle VARCHAR(32) NOT NULL,
+    PRIMARY KEY (tweet_id, account_id)
+);
+
+CREATE INDEX mentions_account_id_idx ON mentions (account_id);
@@ -0,0 +1,13 @@
+-- Tag names are stored normalized (NFKC, lowercased), so each tag has one row.
+CREATE TABLE tags (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE tweet_tags (
+    tweet_id INTEGER NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (tweet_id, tag_id)
+);
+
+CREATE INDEX tweet_tags_tag_id_idx ON tweet_tags (tag_id);
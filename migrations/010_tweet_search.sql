@@ -0,0 +1,4 @@
+-- Trigrams don't depend on word boundaries, so they also index Japanese text.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX tweets_message_trgm_idx ON tweets USING gin (message gin_trgm_ops);
@@ -0,0 +1,4 @@
+-- Prefix searches on handles and on width-folded display names (NORMALIZE needs PostgreSQL 13).
+CREATE INDEX accounts_handle_prefix_idx ON accounts (LOWER(handle) text_pattern_ops);
+CREATE INDEX accounts_display_name_prefix_idx
+    ON accounts (LOWER(NORMALIZE(display_name, NFKC)) text_pattern_ops);
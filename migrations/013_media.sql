@@ -0,0 +1,13 @@
+CREATE TABLE media (
+    id SERIAL PRIMARY KEY,
+    tweet_id INTEGER NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    key VARCHAR(128) NOT NULL UNIQUE,
+    thumbnail_key VARCHAR(128) NOT NULL UNIQUE,
+    content_type VARCHAR(64) NOT NULL,
+    alt_text TEXT NOT NULL DEFAULT '',
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL
+);
+
+CREATE INDEX media_tweet_id_idx ON media (tweet_id);
@@ -0,0 +1,18 @@
+-- One row per URL, shared by every tweet that links to it. A NULL fetched_at means the
+-- background fetcher still has to look at the page; an empty title means there is no card.
+CREATE TABLE link_previews (
+    url TEXT PRIMARY KEY,
+    title TEXT NOT NULL DEFAULT '',
+    description TEXT NOT NULL DEFAULT '',
+    image_url TEXT,
+    fetched_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX link_previews_pending_idx ON link_previews (url) WHERE fetched_at IS NULL;
+
+CREATE TABLE tweet_links (
+    tweet_id INTEGER NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    PRIMARY KEY (tweet_id, position)
+);
@@ -0,0 +1,11 @@
+ALTER TABLE tweets ADD COLUMN edited_at TIMESTAMP WITH TIME ZONE;
+
+-- The earlier versions of edited tweets, each with the time it was written.
+CREATE TABLE tweet_revisions (
+    id SERIAL PRIMARY KEY,
+    tweet_id INTEGER NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+    message TEXT NOT NULL,
+    written_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX tweet_revisions_tweet_id_idx ON tweet_revisions (tweet_id);
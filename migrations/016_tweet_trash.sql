@@ -0,0 +1,4 @@
+-- Deleted tweets are kept for a while so their authors can restore them.
+ALTER TABLE tweets ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX tweets_deleted_at_idx ON tweets (deleted_at) WHERE deleted_at IS NOT NULL;
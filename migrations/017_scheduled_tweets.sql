@@ -0,0 +1,4 @@
+-- Scheduled tweets stay hidden until a worker publishes them and clears publish_at.
+ALTER TABLE tweets ADD COLUMN publish_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX tweets_publish_at_idx ON tweets (publish_at) WHERE publish_at IS NOT NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE drafts (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    message TEXT NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX drafts_account_id_idx ON drafts (account_id, updated_at DESC);
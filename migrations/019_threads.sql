@@ -0,0 +1,4 @@
+-- Threads are chains of replies, each tweet pointing at the one before it.
+ALTER TABLE tweets ADD COLUMN in_reply_to INTEGER REFERENCES tweets (id) ON DELETE SET NULL;
+
+CREATE INDEX tweets_in_reply_to_idx ON tweets (in_reply_to);
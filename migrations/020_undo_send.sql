@@ -0,0 +1,2 @@
+-- Seconds new tweets are held back so they can still be taken back. Zero posts right away.
+ALTER TABLE accounts ADD COLUMN undo_send_seconds INTEGER NOT NULL DEFAULT 0;
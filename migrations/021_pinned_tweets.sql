@@ -0,0 +1,2 @@
+-- The tweet shown at the top of the account's profile. Cleared if the tweet is purged.
+ALTER TABLE accounts ADD COLUMN pinned_tweet_id INTEGER REFERENCES tweets (id) ON DELETE SET NULL;
@@ -0,0 +1,4 @@
+-- Tweets with a warning are shown collapsed behind it, unless the reader always expands them.
+ALTER TABLE tweets ADD COLUMN content_warning TEXT;
+
+ALTER TABLE accounts ADD COLUMN always_expand_content_warnings BOOLEAN NOT NULL DEFAULT FALSE;
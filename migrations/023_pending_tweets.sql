@@ -0,0 +1,3 @@
+-- Tweets held back for the undo delay. They are published like scheduled ones, but aren't
+-- listed with the tweets the author chose to schedule.
+ALTER TABLE tweets ADD COLUMN pending BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- The secret offered while an authenticator is being enrolled, kept until a code from it is
+-- confirmed so the browser never gets to choose it.
+ALTER TABLE accounts ADD COLUMN totp_pending_secret VARCHAR(64);
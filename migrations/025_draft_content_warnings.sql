@@ -0,0 +1 @@
+ALTER TABLE drafts ADD COLUMN content_warning TEXT;
@@ -1,55 +1,223 @@
 use axum::{
-    extract::{Extension, Form},
+    extract::{ConnectInfo, Extension, Form, Query},
     http::Uri,
     response::{Headers, IntoResponse, Redirect},
     routing, Router,
 };
 use serde::Deserialize;
+use std::net::SocketAddr;
 
+use crate::constants::magic_link_enabled;
 use crate::database::RepositoryProvider;
-use crate::services::{self, SessionToken};
+use crate::mailer::MailerProvider;
+use crate::request::{PendingUserContext, UserContext};
+use crate::services::{self, AccountError, SessionError, SessionToken};
 
 pub fn accounts() -> Router {
     Router::new()
         .route("/new", routing::post(post))
         .route("/session", routing::post(new_session))
+        .route("/session/two_factor", routing::post(two_factor_session))
+        .route("/verification", routing::post(resend_verification))
+        .route("/verify", routing::get(verify))
+        .route(
+            "/login_link",
+            routing::get(login_link_session).post(request_login_link),
+        )
+        .route("/password/forgot", routing::post(password_forgot))
+        .route("/password/reset", routing::post(password_reset))
 }
 
 async fn post(
     form: Form<SignUpForm>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Extension(repository_provider): Extension<RepositoryProvider>,
-) -> impl IntoResponse {
+    Extension(mailer): Extension<MailerProvider>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
     let account_repo = repository_provider.accounts();
-    services::create_account(
+    let attempt_repo = repository_provider.login_attempts();
+    let redirect_repo = repository_provider.handle_redirects();
+    let created = services::create_account(
         &account_repo,
+        &redirect_repo,
+        &mailer,
         &form.email,
         &form.password,
         &form.display_name,
+        &form.handle,
     )
     .await;
-    let session_token = services::create_session(&account_repo, &form.email, &form.password).await;
-    redirect_with_session(session_token)
+    if let Err(e) = created {
+        let uri = match e {
+            AccountError::InvalidEmail => "/register?error=email",
+            AccountError::InvalidHandle => "/register?error=handle",
+            AccountError::HandleTaken => "/register?error=handle_taken",
+        };
+        return Err(Redirect::to(Uri::from_static(uri)).into_response());
+    }
+    let session_token = services::create_session(
+        &account_repo,
+        &attempt_repo,
+        &form.email,
+        &form.password,
+        addr.ip(),
+    )
+    .await;
+    redirect_with_session(session_token).map_err(IntoResponse::into_response)
 }
 
 async fn new_session(
     form: Form<SignInForm>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Extension(repository_provider): Extension<RepositoryProvider>,
 ) -> Result<impl IntoResponse, impl IntoResponse> {
     let account_repo = repository_provider.accounts();
-    let session_token = services::create_session(&account_repo, &form.email, &form.password).await;
+    let attempt_repo = repository_provider.login_attempts();
+    let session_token = services::create_session(
+        &account_repo,
+        &attempt_repo,
+        &form.email,
+        &form.password,
+        addr.ip(),
+    )
+    .await;
     redirect_with_session(session_token)
 }
 
-fn redirect_with_session(
-    session: Option<SessionToken>,
+async fn two_factor_session(
+    pending: PendingUserContext,
+    form: Form<TwoFactorForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
 ) -> Result<impl IntoResponse, impl IntoResponse> {
-    if let Some(session_token) = session {
-        let headers = Headers(vec![("Set-Cookie", session_token.cookie())]);
-        let response = Redirect::to(Uri::from_static("/"));
-        Ok((headers, response))
+    let account_repo = repository_provider.accounts();
+    let recovery_repo = repository_provider.recovery_codes();
+    let attempt_repo = repository_provider.login_attempts();
+    let session_token = services::complete_two_factor(
+        &account_repo,
+        &recovery_repo,
+        &attempt_repo,
+        &pending,
+        &form.code,
+    )
+    .await;
+    match session_token {
+        Ok(session_token) => Ok(redirect_with_cookie(session_token)),
+        Err(SessionError::Invalid) => Err(Redirect::to(Uri::from_static(
+            "/login/two_factor?error=invalid",
+        ))),
+        Err(SessionError::TooManyAttempts) => Err(Redirect::to(Uri::from_static(
+            "/login/two_factor?error=throttled",
+        ))),
+    }
+}
+
+async fn resend_verification(
+    user_context: UserContext,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(mailer): Extension<MailerProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    services::resend_verification_email(&account_repo, &mailer, &user_context).await;
+    Redirect::to(Uri::from_static("/verification?sent=true"))
+}
+
+async fn verify(
+    query: Query<VerifyQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    if services::verify_email(&account_repo, &query.token).await {
+        Redirect::to(Uri::from_static("/"))
     } else {
-        Err(Redirect::to(Uri::from_static("/login?error=invalid")))
+        Redirect::to(Uri::from_static("/verification?error=invalid"))
+    }
+}
+
+async fn request_login_link(
+    form: Form<LoginLinkForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(mailer): Extension<MailerProvider>,
+) -> impl IntoResponse {
+    if !magic_link_enabled() {
+        return Redirect::to(Uri::from_static("/login"));
     }
+    let link_repo = repository_provider.login_links();
+    let account_repo = repository_provider.accounts();
+    services::request_login_link(&link_repo, &account_repo, &mailer, &form.email).await;
+    Redirect::to(Uri::from_static("/login/link?sent=true"))
+}
+
+async fn login_link_session(
+    query: Query<LoginLinkQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    if !magic_link_enabled() {
+        return Err(Redirect::to(Uri::from_static("/login")));
+    }
+    let link_repo = repository_provider.login_links();
+    let account_repo = repository_provider.accounts();
+    let session_token =
+        services::create_session_from_login_link(&link_repo, &account_repo, &query.token).await;
+    match session_token {
+        Some(session_token) => Ok(redirect_with_cookie(session_token)),
+        None => Err(Redirect::to(Uri::from_static("/login?error=link"))),
+    }
+}
+
+async fn password_forgot(
+    form: Form<PasswordForgotForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(mailer): Extension<MailerProvider>,
+) -> impl IntoResponse {
+    let reset_repo = repository_provider.password_resets();
+    let account_repo = repository_provider.accounts();
+    services::request_password_reset(&reset_repo, &account_repo, &mailer, &form.email).await;
+    Redirect::to(Uri::from_static("/password/forgot?sent=true"))
+}
+
+async fn password_reset(
+    form: Form<PasswordResetForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let reset_repo = repository_provider.password_resets();
+    let account_repo = repository_provider.accounts();
+    let session_repo = repository_provider.sessions();
+    let reset = services::reset_password(
+        &reset_repo,
+        &account_repo,
+        &session_repo,
+        &form.token,
+        &form.password,
+    )
+    .await;
+    if reset {
+        Redirect::to(Uri::from_static("/login?reset=true"))
+    } else {
+        Redirect::to(Uri::from_static("/password/forgot?error=invalid"))
+    }
+}
+
+fn redirect_with_session(
+    session: Result<SessionToken, SessionError>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    match session {
+        Ok(session_token) => Ok(redirect_with_cookie(session_token)),
+        Err(SessionError::Invalid) => Err(Redirect::to(Uri::from_static("/login?error=invalid"))),
+        Err(SessionError::TooManyAttempts) => {
+            Err(Redirect::to(Uri::from_static("/login?error=throttled")))
+        }
+    }
+}
+
+/// A pending session still has to go through the second login step.
+fn redirect_with_cookie(session_token: SessionToken) -> impl IntoResponse {
+    let headers = Headers(vec![("Set-Cookie", session_token.cookie())]);
+    let response = if session_token.is_pending() {
+        Redirect::to(Uri::from_static("/login/two_factor"))
+    } else {
+        Redirect::to(Uri::from_static("/"))
+    };
+    (headers, response)
 }
 
 #[derive(Deserialize)]
@@ -63,4 +231,36 @@ struct SignUpForm {
     email: String,
     password: String,
     display_name: String,
+    handle: String,
+}
+
+#[derive(Deserialize)]
+struct LoginLinkForm {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct LoginLinkQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct PasswordForgotForm {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct PasswordResetForm {
+    token: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct TwoFactorForm {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyQuery {
+    token: String,
 }
@@ -0,0 +1,143 @@
+use axum::{
+    extract::{Extension, Form, Path, Query},
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Redirect},
+    routing, Json, Router,
+};
+use serde::Deserialize;
+
+use crate::database::RepositoryProvider;
+use crate::jobs;
+use crate::media_store::MediaStoreProvider;
+use crate::request::UserContext;
+use crate::response;
+use crate::services::{self, DraftError, Posted, TweetError};
+use crate::views::DraftSaved;
+
+pub fn drafts() -> Router {
+    Router::new()
+        .route("/", routing::get(get))
+        .route("/save", routing::post(save))
+        .route("/autosave", routing::post(autosave))
+        .route("/:id/publish", routing::post(publish))
+        .route("/:id/delete", routing::post(delete))
+}
+
+async fn get(
+    user_context: UserContext,
+    query: Query<DraftsQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let draft_repo = repository_provider.drafts();
+    let mut drafts = services::list_drafts(&draft_repo, &user_context).await;
+    drafts.error = match query.error.as_deref() {
+        Some("too_long") => {
+            "ツイートは280文字までです（全角文字は2文字、URLは23文字として数えます）。続きを書いて短くしてください。"
+        }
+        Some("save") => "下書きを保存できませんでした。空の下書きは保存されず、下書きは50件までです。",
+        _ => "",
+    }
+    .to_string();
+    response::from_template(drafts)
+}
+
+/// The "save draft" button of the composer.
+async fn save(
+    user_context: UserContext,
+    form: Form<DraftForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let draft_repo = repository_provider.drafts();
+    let id = form.draft_id.parse().ok();
+    let result = services::save_draft(
+        &draft_repo,
+        &user_context,
+        id,
+        &form.message,
+        &form.content_warning,
+    )
+    .await;
+    match result {
+        Ok(_) => Redirect::to(Uri::from_static("/drafts")).into_response(),
+        Err(DraftError::NotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(_) => Redirect::to(Uri::from_static("/drafts?error=save")).into_response(),
+    }
+}
+
+async fn autosave(
+    user_context: UserContext,
+    form: Form<DraftForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let draft_repo = repository_provider.drafts();
+    let id = form.draft_id.parse().ok();
+    let result = services::save_draft(
+        &draft_repo,
+        &user_context,
+        id,
+        &form.message,
+        &form.content_warning,
+    )
+    .await;
+    match result {
+        Err(DraftError::NotFound) => StatusCode::NOT_FOUND.into_response(),
+        result => Json(DraftSaved { id: result.ok() }).into_response(),
+    }
+}
+
+async fn publish(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(media_store): Extension<MediaStoreProvider>,
+) -> impl IntoResponse {
+    let draft_repo = repository_provider.drafts();
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    let result = services::publish_draft(
+        &draft_repo,
+        &tweet_repo,
+        &account_repo,
+        &media_store,
+        &user_context,
+        id,
+    )
+    .await;
+    let uri = match result {
+        Ok(Some(Posted::Pending(id, pending_until))) => {
+            jobs::spawn_pending_publish(repository_provider.clone(), pending_until);
+            return Redirect::to(format!("/?pending={}", id).parse().unwrap());
+        }
+        Ok(_) => "/",
+        Err(TweetError::Unverified) => "/verification",
+        Err(TweetError::TooLong) => "/drafts?error=too_long",
+        Err(_) => "/drafts",
+    };
+    Redirect::to(Uri::from_static(uri))
+}
+
+async fn delete(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let draft_repo = repository_provider.drafts();
+    services::discard_draft(&draft_repo, &user_context, id).await;
+    Redirect::to(Uri::from_static("/drafts"))
+}
+
+/// Also sent by the composer, so other fields may come along and are ignored.
+#[derive(Deserialize)]
+struct DraftForm {
+    message: String,
+    #[serde(default)]
+    content_warning: String,
+    /// Empty until the draft has been saved once.
+    #[serde(default)]
+    draft_id: String,
+}
+
+#[derive(Deserialize)]
+struct DraftsQuery {
+    error: Option<String>,
+}
@@ -0,0 +1,31 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{Headers, IntoResponse},
+    routing, Router,
+};
+
+use crate::images;
+use crate::media_store::{MediaStore, MediaStoreProvider};
+
+pub fn media() -> Router {
+    Router::new().route("/:key", routing::get(get))
+}
+
+/// Media keys are random and never reused, so the files can be cached for good.
+async fn get(
+    Path(key): Path<String>,
+    Extension(media_store): Extension<MediaStoreProvider>,
+) -> impl IntoResponse {
+    match media_store.get(&key).await {
+        Some(bytes) => {
+            let headers = Headers(vec![
+                ("Content-Type", images::content_type(&key)),
+                ("Cache-Control", "public, max-age=31536000, immutable"),
+                ("X-Content-Type-Options", "nosniff"),
+            ]);
+            (headers, bytes).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
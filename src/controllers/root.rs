@@ -1,52 +1,256 @@
 use axum::{
-    extract::{Extension, Query},
-    response::{Headers, IntoResponse},
+    extract::{Extension, Path, Query},
+    http::{StatusCode, Uri},
+    response::{Headers, IntoResponse, Redirect},
     routing, Router,
 };
 use serde::Deserialize;
 
-use crate::controllers::{accounts, tweets};
+use crate::constants::{magic_link_enabled, tweet_edit_window};
+use crate::controllers::{accounts, drafts, media, searches, settings, tweets};
 use crate::database::{self, RepositoryProvider};
-use crate::request::UserContext;
+use crate::mailer;
+use crate::media_store;
+use crate::request::{PendingUserContext, UserContext};
 use crate::response;
-use crate::services;
-use crate::views::{SignIn, SignUp};
+use crate::services::{self, ProfileLookup};
+use crate::views::{
+    LoginLink, PasswordForgot, PasswordReset, SignIn, SignUp, TwoFactorLogin, Verification,
+};
 
 pub async fn app() -> Router {
     let database_layer = database::layer().await;
     Router::new()
         .route("/", routing::get(get))
+        .route("/mentions", routing::get(mentions))
         .route("/login", routing::get(login))
+        .route("/login/link", routing::get(login_link))
+        .route("/login/two_factor", routing::get(login_two_factor))
         .route("/register", routing::get(register))
+        .route("/verification", routing::get(verification))
+        .route("/password/forgot", routing::get(password_forgot))
+        .route("/password/reset", routing::get(password_reset))
+        .route("/@:handle", routing::get(profile))
+        .route("/tags/:tag", routing::get(tag))
         .nest("/tweets", tweets::tweets())
+        .nest("/drafts", drafts::drafts())
+        .nest("/media", media::media())
         .nest("/accounts", accounts::accounts())
+        .nest("/search", searches::searches())
+        .nest("/settings", settings::settings())
         .layer(database_layer)
+        .layer(mailer::layer())
+        .layer(media_store::layer())
 }
 
 async fn get(
-    _: UserContext,
+    user_context: UserContext,
+    query: Query<HomeQuery>,
     Extension(repository_provider): Extension<RepositoryProvider>,
 ) -> impl IntoResponse {
     let tweet_repo = repository_provider.tweets();
     let account_repo = repository_provider.accounts();
-    let home = services::list_tweets(&tweet_repo, &account_repo).await;
+    let draft_repo = repository_provider.drafts();
+    let mut home = services::list_tweets(
+        &tweet_repo,
+        &account_repo,
+        &user_context,
+        tweet_edit_window(),
+    )
+    .await;
+    if let Some(id) = query.draft {
+        home.draft = services::find_draft(&draft_repo, &user_context, id).await;
+    }
+    if let Some(id) = query.pending {
+        home.pending = services::pending_tweet(&tweet_repo, &user_context, id).await;
+    }
+    if query.undone.is_some() {
+        home.notice = "ツイートを取り消しました。ゴミ箱から元に戻せます。".to_string();
+    }
+    home.error = match query.error.as_deref() {
+        Some("too_long") => {
+            "ツイートは280文字までです（全角文字は2文字、URLは23文字として数えます）。"
+        }
+        Some("too_many_images") => "画像は4枚まで添付できます。",
+        Some("image_too_large") => "画像が大きすぎます。1枚5MBまでです。",
+        Some("unsupported_image") => "JPEG・PNG・GIF・WebP以外の画像は添付できません。",
+        Some("alt_text") => "画像の説明は1000文字以内で入力してください。",
+        Some("content_warning") => "注意書きは100文字以内で入力してください。",
+        Some("schedule") => "投稿日時は未来の日時を指定してください。",
+        Some("undo_too_late") => "ツイートはすでに公開されたため、取り消せませんでした。",
+        _ => "",
+    }
+    .to_string();
     response::from_template(home)
 }
 
+async fn mentions(
+    user_context: UserContext,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    let mentions = services::list_mentions(
+        &tweet_repo,
+        &account_repo,
+        &user_context,
+        tweet_edit_window(),
+    )
+    .await;
+    response::from_template(mentions)
+}
+
+async fn tag(
+    user_context: UserContext,
+    Path(tag): Path<String>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    let tag = services::list_tagged(
+        &tweet_repo,
+        &account_repo,
+        &user_context,
+        tweet_edit_window(),
+        &tag,
+    )
+    .await;
+    response::from_template(tag)
+}
+
+async fn profile(
+    user_context: UserContext,
+    Path(handle): Path<String>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let redirect_repo = repository_provider.handle_redirects();
+    let tweet_repo = repository_provider.tweets();
+    let lookup = services::profile(
+        &account_repo,
+        &redirect_repo,
+        &tweet_repo,
+        &user_context,
+        tweet_edit_window(),
+        &handle,
+    )
+    .await;
+    match lookup {
+        ProfileLookup::Found(profile) => response::from_template(*profile).into_response(),
+        ProfileLookup::Moved(handle) => {
+            let uri: Uri = format!("/@{}", handle).parse().unwrap();
+            Redirect::permanent(uri).into_response()
+        }
+        ProfileLookup::Deleted(page) => {
+            (StatusCode::GONE, response::from_template(page)).into_response()
+        }
+        ProfileLookup::NotFound => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
 async fn login(query: Query<LoginQuery>) -> impl IntoResponse {
     let empty_session_token = services::clear_session();
     let headers = Headers(vec![("Set-Cookie", empty_session_token.cookie())]);
     let response = response::from_template(SignIn {
         error: query.error.is_some(),
+        throttled: query.error.as_deref() == Some("throttled"),
+        password_reset: query.reset.is_some(),
+        login_link_invalid: query.error.as_deref() == Some("link"),
+        login_link_enabled: magic_link_enabled(),
+        account_deleted: query.deleted.as_deref() == Some("true"),
+        account_deletion_scheduled: query.deleted.as_deref() == Some("scheduled"),
     });
     (headers, response)
 }
 
-async fn register() -> impl IntoResponse {
-    response::from_template(SignUp)
+async fn login_link(query: Query<LoginLinkQuery>) -> Result<impl IntoResponse, impl IntoResponse> {
+    if !magic_link_enabled() {
+        return Err(Redirect::to(Uri::from_static("/login")));
+    }
+    Ok(response::from_template(LoginLink {
+        sent: query.sent.is_some(),
+    }))
+}
+
+async fn login_two_factor(_: PendingUserContext, query: Query<LoginQuery>) -> impl IntoResponse {
+    response::from_template(TwoFactorLogin {
+        error: query.error.is_some(),
+        throttled: query.error.as_deref() == Some("throttled"),
+    })
+}
+
+async fn register(query: Query<RegisterQuery>) -> impl IntoResponse {
+    let error = match query.error.as_deref() {
+        Some("email") => "メールアドレスの形式が正しくありません。",
+        Some("handle") => "ユーザー名は半角英数字とアンダースコアの15文字以内で入力してください。",
+        Some("handle_taken") => "このユーザー名はすでに使われています。",
+        _ => "",
+    };
+    response::from_template(SignUp {
+        error: error.to_string(),
+    })
+}
+
+async fn verification(_: UserContext, query: Query<VerificationQuery>) -> impl IntoResponse {
+    response::from_template(Verification {
+        sent: query.sent.is_some(),
+        invalid: query.error.is_some(),
+    })
+}
+
+async fn password_forgot(query: Query<PasswordForgotQuery>) -> impl IntoResponse {
+    response::from_template(PasswordForgot {
+        sent: query.sent.is_some(),
+        invalid: query.error.is_some(),
+    })
+}
+
+async fn password_reset(query: Query<PasswordResetQuery>) -> impl IntoResponse {
+    response::from_template(PasswordReset {
+        token: query.token.clone(),
+    })
+}
+
+#[derive(Deserialize)]
+struct HomeQuery {
+    error: Option<String>,
+    /// The draft to resume in the composer.
+    draft: Option<i32>,
+    /// The tweet just posted and still held for the undo delay.
+    pending: Option<i32>,
+    undone: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct LoginQuery {
     error: Option<String>,
+    reset: Option<String>,
+    deleted: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginLinkQuery {
+    sent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RegisterQuery {
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PasswordForgotQuery {
+    sent: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PasswordResetQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct VerificationQuery {
+    sent: Option<String>,
+    error: Option<String>,
 }
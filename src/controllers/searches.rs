@@ -0,0 +1,103 @@
+use axum::{
+    extract::{Extension, Form, Path, Query},
+    http::Uri,
+    response::{IntoResponse, Redirect},
+    routing, Json, Router,
+};
+use serde::Deserialize;
+
+use crate::constants::tweet_edit_window;
+use crate::database::RepositoryProvider;
+use crate::repositories::SearchOrder;
+use crate::request::UserContext;
+use crate::response;
+use crate::services;
+
+pub fn searches() -> Router {
+    Router::new()
+        .route("/", routing::get(get))
+        .route("/accounts", routing::get(accounts))
+        .route("/accounts/typeahead", routing::get(typeahead))
+        .route("/saved", routing::post(save))
+        .route("/saved/:id/delete", routing::post(delete_saved))
+}
+
+async fn get(
+    user_context: UserContext,
+    query: Query<SearchQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    let saved_repo = repository_provider.saved_searches();
+    let order = match query.order.as_deref() {
+        Some("recency") => SearchOrder::Recency,
+        _ => SearchOrder::Relevance,
+    };
+    let search = services::search_tweets(
+        &tweet_repo,
+        &account_repo,
+        &saved_repo,
+        &user_context,
+        tweet_edit_window(),
+        query.q.as_deref().unwrap_or(""),
+        order,
+    )
+    .await;
+    response::from_template(search)
+}
+
+async fn accounts(
+    _: UserContext,
+    query: Query<SearchQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let search = services::search_accounts(&account_repo, query.q.as_deref().unwrap_or("")).await;
+    response::from_template(search)
+}
+
+async fn typeahead(
+    _: UserContext,
+    query: Query<SearchQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let suggestions =
+        services::suggest_accounts(&account_repo, query.q.as_deref().unwrap_or("")).await;
+    Json(suggestions)
+}
+
+async fn save(
+    user_context: UserContext,
+    form: Form<SaveForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let saved_repo = repository_provider.saved_searches();
+    services::save_search(&saved_repo, &user_context, &form.q).await;
+    let uri: Uri = format!("/search?q={}", urlencoding::encode(form.q.trim()))
+        .parse()
+        .unwrap();
+    Redirect::to(uri)
+}
+
+async fn delete_saved(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let saved_repo = repository_provider.saved_searches();
+    services::delete_saved_search(&saved_repo, &user_context, id).await;
+    Redirect::to(Uri::from_static("/search"))
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: Option<String>,
+    order: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SaveForm {
+    q: String,
+}
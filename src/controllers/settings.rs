@@ -0,0 +1,352 @@
+use axum::{
+    extract::{Extension, Form, Query},
+    http::Uri,
+    response::{Headers, IntoResponse, Redirect},
+    routing, Router,
+};
+use serde::Deserialize;
+
+use crate::constants::account_deletion_grace_days;
+use crate::database::RepositoryProvider;
+use crate::mailer::MailerProvider;
+use crate::media_store::MediaStoreProvider;
+use crate::request::UserContext;
+use crate::response;
+use crate::services::{self, AccountDeletion, SettingsError};
+use crate::views::TwoFactorRecoveryCodes;
+
+pub fn settings() -> Router {
+    Router::new()
+        .route("/", routing::get(get))
+        .route("/display_name", routing::post(display_name))
+        .route("/handle", routing::post(handle))
+        .route("/email", routing::post(email))
+        .route("/password", routing::post(password))
+        .route("/undo_send", routing::post(undo_send))
+        .route("/content_warnings", routing::post(content_warnings))
+        .route("/delete", routing::post(delete))
+        .route("/two_factor", routing::get(two_factor))
+        .route("/two_factor/enable", routing::post(enable_two_factor))
+        .route("/two_factor/disable", routing::post(disable_two_factor))
+        .route("/two_factor/reenroll", routing::post(reenroll_two_factor))
+        .route(
+            "/two_factor/reenroll/cancel",
+            routing::post(cancel_two_factor_reenrollment),
+        )
+        .route(
+            "/two_factor/recovery_codes",
+            routing::post(regenerate_recovery_codes),
+        )
+}
+
+async fn get(
+    user_context: UserContext,
+    query: Query<SettingsQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let mut page = services::settings(&account_repo, &user_context).await;
+    page.notice = match query.updated.as_deref() {
+        Some("display_name") => "表示名を変更しました。",
+        Some("handle") => "ユーザー名を変更しました。",
+        Some("email") => "メールアドレスを変更しました。新しいアドレスに届いた確認メールのリンクを開いてください。",
+        Some("password") => "パスワードを変更しました。",
+        Some("undo_send") => "送信を取り消せる時間を変更しました。",
+        Some("content_warnings") => "注意書きのあるツイートの表示を変更しました。",
+        _ => "",
+    }
+    .to_string();
+    page.error = match query.error.as_deref() {
+        Some("display_name") => "表示名は1文字以上50文字以内で入力してください。",
+        Some("handle") => "ユーザー名は半角英数字とアンダースコアの15文字以内で入力してください。",
+        Some("handle_taken") => "このユーザー名はすでに使われています。",
+        Some("password") => "パスワードが違います。",
+        Some("email") => "メールアドレスの形式が正しくありません。",
+        Some("email_taken") => "このメールアドレスはすでに使われています。",
+        Some("undo_send") => "送信を取り消せる時間は0秒から30秒で指定してください。",
+        _ => "",
+    }
+    .to_string();
+    response::from_template(page)
+}
+
+async fn display_name(
+    user_context: UserContext,
+    form: Form<DisplayNameForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let result =
+        services::change_display_name(&account_repo, &user_context, &form.display_name).await;
+    redirect_to_settings(result, "/settings?updated=display_name")
+}
+
+async fn handle(
+    user_context: UserContext,
+    form: Form<HandleForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let redirect_repo = repository_provider.handle_redirects();
+    let result =
+        services::change_handle(&account_repo, &redirect_repo, &user_context, &form.handle).await;
+    redirect_to_settings(result, "/settings?updated=handle")
+}
+
+async fn email(
+    user_context: UserContext,
+    form: Form<EmailForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(mailer): Extension<MailerProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let result = services::change_email(
+        &account_repo,
+        &mailer,
+        &user_context,
+        &form.email,
+        &form.password,
+    )
+    .await;
+    redirect_to_settings(result, "/settings?updated=email")
+}
+
+async fn password(
+    user_context: UserContext,
+    form: Form<PasswordChangeForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    let account_repo = repository_provider.accounts();
+    let session_repo = repository_provider.sessions();
+    let session_token = services::change_password(
+        &account_repo,
+        &session_repo,
+        &user_context,
+        &form.current_password,
+        &form.new_password,
+    )
+    .await;
+    match session_token {
+        Ok(session_token) => {
+            let headers = Headers(vec![("Set-Cookie", session_token.cookie())]);
+            Ok((
+                headers,
+                Redirect::to(Uri::from_static("/settings?updated=password")),
+            ))
+        }
+        Err(e) => Err(redirect_to_settings(Err(e), "")),
+    }
+}
+
+async fn undo_send(
+    user_context: UserContext,
+    form: Form<UndoSendForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let result = services::change_undo_send_delay(&account_repo, &user_context, form.seconds).await;
+    redirect_to_settings(result, "/settings?updated=undo_send")
+}
+
+async fn content_warnings(
+    user_context: UserContext,
+    form: Form<ContentWarningsForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    services::change_content_warning_display(
+        &account_repo,
+        &user_context,
+        form.always_expand.is_some(),
+    )
+    .await;
+    Redirect::to(Uri::from_static("/settings?updated=content_warnings"))
+}
+
+async fn delete(
+    user_context: UserContext,
+    form: Form<PasswordForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(media_store): Extension<MediaStoreProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let tweet_repo = repository_provider.tweets();
+    let session_repo = repository_provider.sessions();
+    let result = services::delete_account(
+        &account_repo,
+        &tweet_repo,
+        &session_repo,
+        &media_store,
+        &user_context,
+        account_deletion_grace_days(),
+        &form.password,
+    )
+    .await;
+    match result {
+        Ok(AccountDeletion::Scheduled) => {
+            Redirect::to(Uri::from_static("/login?deleted=scheduled"))
+        }
+        Ok(AccountDeletion::Deleted) => Redirect::to(Uri::from_static("/login?deleted=true")),
+        Err(e) => redirect_to_settings(Err(e), ""),
+    }
+}
+
+fn redirect_to_settings(result: Result<(), SettingsError>, success: &'static str) -> Redirect {
+    let uri = match result {
+        Ok(()) => success,
+        Err(SettingsError::InvalidDisplayName) => "/settings?error=display_name",
+        Err(SettingsError::InvalidHandle) => "/settings?error=handle",
+        Err(SettingsError::HandleTaken) => "/settings?error=handle_taken",
+        Err(SettingsError::InvalidPassword) => "/settings?error=password",
+        Err(SettingsError::InvalidEmail) => "/settings?error=email",
+        Err(SettingsError::EmailTaken) => "/settings?error=email_taken",
+        Err(SettingsError::InvalidUndoSendDelay) => "/settings?error=undo_send",
+    };
+    Redirect::to(Uri::from_static(uri))
+}
+
+async fn two_factor(
+    user_context: UserContext,
+    query: Query<SettingsQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let mut page = services::two_factor_settings(&account_repo, &user_context).await;
+    page.error = query.error.is_some();
+    response::from_template(page)
+}
+
+async fn enable_two_factor(
+    user_context: UserContext,
+    form: Form<EnableTwoFactorForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    let account_repo = repository_provider.accounts();
+    let recovery_repo = repository_provider.recovery_codes();
+    let codes = services::enable_two_factor(
+        &account_repo,
+        &recovery_repo,
+        &user_context,
+        &form.code,
+        &form.password,
+    )
+    .await;
+    recovery_codes_or_error(codes)
+}
+
+async fn disable_two_factor(
+    user_context: UserContext,
+    form: Form<PasswordForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let recovery_repo = repository_provider.recovery_codes();
+    let disabled =
+        services::disable_two_factor(&account_repo, &recovery_repo, &user_context, &form.password)
+            .await;
+    if disabled {
+        Redirect::to(Uri::from_static("/settings/two_factor"))
+    } else {
+        Redirect::to(Uri::from_static("/settings/two_factor?error=invalid"))
+    }
+}
+
+async fn reenroll_two_factor(
+    user_context: UserContext,
+    form: Form<PasswordForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    if services::reenroll_two_factor(&account_repo, &user_context, &form.password).await {
+        Redirect::to(Uri::from_static("/settings/two_factor"))
+    } else {
+        Redirect::to(Uri::from_static("/settings/two_factor?error=invalid"))
+    }
+}
+
+async fn cancel_two_factor_reenrollment(
+    user_context: UserContext,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    services::cancel_two_factor_reenrollment(&account_repo, &user_context).await;
+    Redirect::to(Uri::from_static("/settings/two_factor"))
+}
+
+async fn regenerate_recovery_codes(
+    user_context: UserContext,
+    form: Form<PasswordForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    let account_repo = repository_provider.accounts();
+    let recovery_repo = repository_provider.recovery_codes();
+    let codes = services::regenerate_recovery_codes(
+        &account_repo,
+        &recovery_repo,
+        &user_context,
+        &form.password,
+    )
+    .await;
+    recovery_codes_or_error(codes)
+}
+
+fn recovery_codes_or_error(
+    codes: Option<Vec<String>>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+    match codes {
+        Some(codes) => Ok(response::from_template(TwoFactorRecoveryCodes { codes })),
+        None => Err(Redirect::to(Uri::from_static(
+            "/settings/two_factor?error=invalid",
+        ))),
+    }
+}
+
+#[derive(Deserialize)]
+struct SettingsQuery {
+    updated: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DisplayNameForm {
+    display_name: String,
+}
+
+#[derive(Deserialize)]
+struct HandleForm {
+    handle: String,
+}
+
+#[derive(Deserialize)]
+struct EmailForm {
+    email: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct PasswordChangeForm {
+    current_password: String,
+    new_password: String,
+}
+
+#[derive(Deserialize)]
+struct UndoSendForm {
+    seconds: i32,
+}
+
+/// The checkbox is only sent when checked.
+#[derive(Deserialize)]
+struct ContentWarningsForm {
+    always_expand: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EnableTwoFactorForm {
+    code: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct PasswordForm {
+    password: String,
+}
@@ -1,42 +1,484 @@
 use axum::{
-    extract::{Extension, Form, Path},
-    http::Uri,
+    extract::{ContentLengthLimit, Extension, Form, Multipart, Path, Query},
+    http::{StatusCode, Uri},
     response::{IntoResponse, Redirect},
-    routing, Router,
+    routing, Json, Router,
 };
 use serde::Deserialize;
+use std::collections::HashMap;
 
+use crate::constants::tweet_edit_window;
 use crate::database::RepositoryProvider;
+use crate::images::MAX_IMAGE_BYTES;
+use crate::jobs;
+use crate::media_store::MediaStoreProvider;
 use crate::request::UserContext;
-use crate::services;
+use crate::response;
+use crate::services::{self, ImageUpload, NewTweet, Posted, TweetError};
+use crate::views::ThreadComposer;
+
+/// Room for four images at the size limit, plus the rest of the form.
+const MAX_POST_BYTES: u64 = 4 * MAX_IMAGE_BYTES as u64 + 64 * 1024;
 
 pub fn tweets() -> Router {
     Router::new()
         .route("/new", routing::post(post))
+        .route("/length", routing::post(length))
+        .route("/thread", routing::get(compose_thread).post(post_thread))
+        .route("/scheduled", routing::get(scheduled))
+        .route(
+            "/scheduled/:id",
+            routing::get(edit_scheduled).post(update_scheduled),
+        )
+        .route("/scheduled/:id/cancel", routing::post(cancel_scheduled))
+        .route("/trash", routing::get(trash))
+        .route("/unpin", routing::post(unpin))
+        .route("/:id/edit", routing::get(edit).post(update))
+        .route("/:id/history", routing::get(history))
+        .route("/:id/thread", routing::get(thread))
+        .route("/:id/undo", routing::post(undo))
         .route("/:id/delete", routing::post(delete))
+        .route("/:id/restore", routing::post(restore))
+        .route("/:id/pin", routing::post(pin))
 }
 
 async fn post(
     user_context: UserContext,
-    form: Form<TweetForm>,
+    ContentLengthLimit(multipart): ContentLengthLimit<Multipart, MAX_POST_BYTES>,
     Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(media_store): Extension<MediaStoreProvider>,
 ) -> impl IntoResponse {
+    let form = match TweetForm::read(multipart).await {
+        Some(form) => form,
+        None => return Redirect::to(Uri::from_static("/?error=unsupported_image")),
+    };
+    let publish_at = match services::parse_publish_at(&form.publish_at, &form.utc_offset) {
+        Ok(publish_at) => publish_at,
+        Err(_) => return Redirect::to(Uri::from_static("/?error=schedule")),
+    };
     let tweet_repo = repository_provider.tweets();
-    services::create_tweet(&tweet_repo, &user_context, &form.message).await;
-    Redirect::to(Uri::from_static("/"))
+    let account_repo = repository_provider.accounts();
+    let result = services::create_tweet(
+        &tweet_repo,
+        &account_repo,
+        &media_store,
+        &user_context,
+        NewTweet {
+            message: form.message,
+            content_warning: form.content_warning,
+            images: form.images,
+            publish_at,
+        },
+    )
+    .await;
+    if let (Ok(_), Some(id)) = (&result, form.draft_id) {
+        let draft_repo = repository_provider.drafts();
+        services::discard_draft(&draft_repo, &user_context, id).await;
+    }
+    let uri = match result {
+        Ok(Posted::Published) => "/",
+        Ok(Posted::Pending(id, pending_until)) => {
+            jobs::spawn_pending_publish(repository_provider.clone(), pending_until);
+            return Redirect::to(format!("/?pending={}", id).parse().unwrap());
+        }
+        Ok(Posted::Scheduled) => "/tweets/scheduled",
+        Err(TweetError::Unverified) => "/verification",
+        Err(TweetError::TooLong) => "/?error=too_long",
+        Err(TweetError::TooManyImages) => "/?error=too_many_images",
+        Err(TweetError::ImageTooLarge) => "/?error=image_too_large",
+        Err(TweetError::UnsupportedImage) => "/?error=unsupported_image",
+        Err(TweetError::AltTextTooLong) => "/?error=alt_text",
+        Err(TweetError::ContentWarningTooLong) => "/?error=content_warning",
+        Err(TweetError::InvalidSchedule) => "/?error=schedule",
+        Err(TweetError::NotEditable | TweetError::EmptyThread | TweetError::ThreadTooLong) => "/",
+    };
+    Redirect::to(Uri::from_static(uri))
+}
+
+async fn compose_thread(_: UserContext) -> impl IntoResponse {
+    response::from_template(ThreadComposer {
+        segments: vec![String::new(), String::new()],
+        numbered: false,
+        content_warning: String::new(),
+        error: String::new(),
+    })
+}
+
+/// Shows the composer again with what was written if the thread can't be posted.
+async fn post_thread(
+    user_context: UserContext,
+    Form(form): Form<HashMap<String, String>>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+    Extension(media_store): Extension<MediaStoreProvider>,
+) -> impl IntoResponse {
+    let thread = ThreadForm::from(form);
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    let result = services::create_thread(
+        &tweet_repo,
+        &account_repo,
+        &media_store,
+        &user_context,
+        &thread.segments,
+        thread.numbered,
+        &thread.content_warning,
+    )
+    .await;
+    let error = match result {
+        Ok(()) => return Redirect::to(Uri::from_static("/")).into_response(),
+        Err(TweetError::Unverified) => {
+            return Redirect::to(Uri::from_static("/verification")).into_response()
+        }
+        Err(TweetError::TooLong) => {
+            "280文字を超える文があり、分けられませんでした。句点などで区切ってください。"
+        }
+        Err(TweetError::ThreadTooLong) => "スレッドは25件までです。",
+        Err(TweetError::ContentWarningTooLong) => "注意書きは100文字以内で入力してください。",
+        Err(_) => "ツイートを入力してください。",
+    };
+    response::from_template(ThreadComposer {
+        segments: thread.segments,
+        numbered: thread.numbered,
+        content_warning: thread.content_warning,
+        error: error.to_string(),
+    })
+    .into_response()
+}
+
+async fn thread(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    match services::thread(
+        &tweet_repo,
+        &account_repo,
+        &user_context,
+        tweet_edit_window(),
+        id,
+    )
+    .await
+    {
+        Some(thread) => response::from_template(thread).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn length(_: UserContext, form: Form<LengthForm>) -> impl IntoResponse {
+    Json(services::measure_tweet(&form.message))
+}
+
+async fn edit(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    query: Query<EditQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let error = match query.error.as_deref() {
+        Some("too_long") => {
+            "ツイートは280文字までです（全角文字は2文字、URLは23文字として数えます）。"
+        }
+        _ => "",
+    };
+    match services::edit_form(&tweet_repo, &user_context, tweet_edit_window(), id, error).await {
+        Some(form) => response::from_template(form).into_response(),
+        None => Redirect::to(Uri::from_static("/")).into_response(),
+    }
+}
+
+async fn update(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    form: Form<EditForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    let result = services::edit_tweet(
+        &tweet_repo,
+        &account_repo,
+        &user_context,
+        tweet_edit_window(),
+        id,
+        &form.message,
+    )
+    .await;
+    let uri = match result {
+        Err(TweetError::TooLong) => format!("/tweets/{}/edit?error=too_long", id),
+        _ => "/".to_string(),
+    };
+    Redirect::to(uri.parse().unwrap())
+}
+
+async fn history(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    match services::tweet_history(
+        &tweet_repo,
+        &account_repo,
+        &user_context,
+        tweet_edit_window(),
+        id,
+    )
+    .await
+    {
+        Some(history) => response::from_template(history).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
 }
 
 async fn delete(
-    _: UserContext,
+    user_context: UserContext,
     Path(id): Path<i32>,
     Extension(repository_provider): Extension<RepositoryProvider>,
 ) -> impl IntoResponse {
     let tweet_repo = repository_provider.tweets();
-    services::delete_tweet(&tweet_repo, id).await;
+    let account_repo = repository_provider.accounts();
+    services::delete_tweet(&tweet_repo, &account_repo, &user_context, id).await;
     Redirect::to(Uri::from_static("/"))
 }
 
-#[derive(Deserialize)]
+async fn pin(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let tweet_repo = repository_provider.tweets();
+    let handle = services::pin_tweet(&account_repo, &tweet_repo, &user_context, id).await;
+    Redirect::to(format!("/@{}", handle).parse().unwrap())
+}
+
+async fn unpin(
+    user_context: UserContext,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let account_repo = repository_provider.accounts();
+    let handle = services::unpin_tweet(&account_repo, &user_context).await;
+    Redirect::to(format!("/@{}", handle).parse().unwrap())
+}
+
+async fn scheduled(
+    user_context: UserContext,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    response::from_template(services::list_scheduled(&tweet_repo, &user_context).await)
+}
+
+async fn edit_scheduled(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    query: Query<EditQuery>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let error = match query.error.as_deref() {
+        Some("too_long") => {
+            "ツイートは280文字までです（全角文字は2文字、URLは23文字として数えます）。"
+        }
+        Some("schedule") => "投稿日時は未来の日時を指定してください。",
+        _ => "",
+    };
+    match services::scheduled_edit_form(&tweet_repo, &user_context, id, error).await {
+        Some(form) => response::from_template(form).into_response(),
+        None => Redirect::to(Uri::from_static("/tweets/scheduled")).into_response(),
+    }
+}
+
+async fn update_scheduled(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    form: Form<ScheduledEditForm>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    let account_repo = repository_provider.accounts();
+    let publish_at = services::parse_publish_at(&form.publish_at, &form.utc_offset)
+        .ok()
+        .flatten();
+    let result = services::edit_scheduled_tweet(
+        &tweet_repo,
+        &account_repo,
+        &user_context,
+        id,
+        &form.message,
+        publish_at,
+    )
+    .await;
+    let uri = match result {
+        Err(TweetError::TooLong) => format!("/tweets/scheduled/{}?error=too_long", id),
+        Err(TweetError::InvalidSchedule) => format!("/tweets/scheduled/{}?error=schedule", id),
+        _ => "/tweets/scheduled".to_string(),
+    };
+    Redirect::to(uri.parse().unwrap())
+}
+
+async fn cancel_scheduled(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    services::cancel_scheduled_tweet(&tweet_repo, &user_context, id).await;
+    Redirect::to(Uri::from_static("/tweets/scheduled"))
+}
+
+/// Takes back a tweet still held for the undo delay.
+async fn undo(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    if services::cancel_scheduled_tweet(&tweet_repo, &user_context, id).await {
+        Redirect::to(Uri::from_static("/?undone=true"))
+    } else {
+        Redirect::to(Uri::from_static("/?error=undo_too_late"))
+    }
+}
+
+async fn trash(
+    user_context: UserContext,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    response::from_template(services::list_trash(&tweet_repo, &user_context).await)
+}
+
+async fn restore(
+    user_context: UserContext,
+    Path(id): Path<i32>,
+    Extension(repository_provider): Extension<RepositoryProvider>,
+) -> impl IntoResponse {
+    let tweet_repo = repository_provider.tweets();
+    if services::restore_tweet(&tweet_repo, &user_context, id).await {
+        Redirect::to(Uri::from_static("/"))
+    } else {
+        Redirect::to(Uri::from_static("/tweets/trash"))
+    }
+}
+
+/// The composer sends `message` and up to four `image_N` / `alt_text_N` pairs, and optionally
+/// `publish_at` in the user's time zone along with its `utc_offset`. A resumed draft comes with
+/// its `draft_id`, and is removed once posted.
 struct TweetForm {
     message: String,
+    content_warning: String,
+    images: Vec<ImageUpload>,
+    publish_at: String,
+    utc_offset: String,
+    draft_id: Option<i32>,
+}
+
+impl TweetForm {
+    async fn read(mut multipart: Multipart) -> Option<TweetForm> {
+        let mut message = String::new();
+        let mut content_warning = String::new();
+        let mut publish_at = String::new();
+        let mut utc_offset = String::new();
+        let mut draft_id = None;
+        let mut images: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut alt_texts: Vec<(String, String)> = Vec::new();
+        while let Some(field) = multipart.next_field().await.ok()? {
+            let name = field.name().unwrap_or_default().to_string();
+            if name == "message" {
+                message = field.text().await.ok()?;
+            } else if name == "content_warning" {
+                content_warning = field.text().await.ok()?;
+            } else if name == "publish_at" {
+                publish_at = field.text().await.ok()?;
+            } else if name == "utc_offset" {
+                utc_offset = field.text().await.ok()?;
+            } else if name == "draft_id" {
+                draft_id = field.text().await.ok()?.parse().ok();
+            } else if let Some(index) = name.strip_prefix("image_") {
+                let index = index.to_string();
+                let bytes = field.bytes().await.ok()?;
+                // An empty file input is still sent, with no content.
+                if !bytes.is_empty() {
+                    images.push((index, bytes.to_vec()));
+                }
+            } else if let Some(index) = name.strip_prefix("alt_text_") {
+                let index = index.to_string();
+                alt_texts.push((index, field.text().await.ok()?));
+            }
+        }
+
+        let images = images
+            .into_iter()
+            .map(|(index, bytes)| ImageUpload {
+                bytes,
+                alt_text: alt_texts
+                    .iter()
+                    .find(|x| x.0 == index)
+                    .map(|x| x.1.clone())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        Some(TweetForm {
+            message,
+            content_warning,
+            images,
+            publish_at,
+            utc_offset,
+            draft_id,
+        })
+    }
+}
+
+/// The thread composer sends `segment_N` for each tweet in order, and `numbered` if checked.
+struct ThreadForm {
+    segments: Vec<String>,
+    numbered: bool,
+    content_warning: String,
+}
+
+impl From<HashMap<String, String>> for ThreadForm {
+    fn from(mut form: HashMap<String, String>) -> Self {
+        let numbered = form.remove("numbered").is_some();
+        let content_warning = form.remove("content_warning").unwrap_or_default();
+        let mut segments = form
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let index = name.strip_prefix("segment_")?.parse::<usize>().ok()?;
+                Some((index, value))
+            })
+            .collect::<Vec<_>>();
+        segments.sort_by_key(|x| x.0);
+        ThreadForm {
+            segments: segments.into_iter().map(|x| x.1).collect(),
+            numbered,
+            content_warning,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LengthForm {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct EditForm {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ScheduledEditForm {
+    message: String,
+    publish_at: String,
+    utc_offset: String,
+}
+
+#[derive(Deserialize)]
+struct EditQuery {
+    error: Option<String>,
 }
@@ -1,28 +1,79 @@
 use crate::constants::database_url;
-use axum::AddExtensionLayer;
+use axum::extract::Extension;
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
 use tokio_postgres::NoTls;
 
-use crate::repos_impl::{AccountsImpl, TweetsImpl};
+use crate::repos_impl::{
+    AccountsImpl, DraftsImpl, HandleRedirectsImpl, LinkPreviewsImpl, LoginAttemptsImpl,
+    LoginLinksImpl, PasswordResetsImpl, RecoveryCodesImpl, SavedSearchesImpl, SessionsImpl,
+    TweetsImpl,
+};
 
 pub type ConnectionPool = Pool<PostgresConnectionManager<NoTls>>;
 
-pub async fn layer() -> AddExtensionLayer<RepositoryProvider> {
+/// Makes `%`, `_` and `\` in a LIKE pattern match literally.
+pub fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub async fn layer() -> Extension<RepositoryProvider> {
+    Extension(provider().await)
+}
+
+pub async fn provider() -> RepositoryProvider {
     let manager = PostgresConnectionManager::new_from_stringlike(database_url(), NoTls).unwrap();
     let pool = Pool::builder().build(manager).await.unwrap();
-    AddExtensionLayer::new(RepositoryProvider(pool))
+    RepositoryProvider(pool)
 }
 
 #[derive(Clone)]
 pub struct RepositoryProvider(ConnectionPool);
 
 impl RepositoryProvider {
-    pub fn tweets(&self) -> TweetsImpl {
+    pub fn tweets(&self) -> TweetsImpl<'_> {
         TweetsImpl { pool: &self.0 }
     }
 
-    pub fn accounts(&self) -> AccountsImpl {
+    pub fn accounts(&self) -> AccountsImpl<'_> {
         AccountsImpl { pool: &self.0 }
     }
+
+    pub fn drafts(&self) -> DraftsImpl<'_> {
+        DraftsImpl { pool: &self.0 }
+    }
+
+    pub fn handle_redirects(&self) -> HandleRedirectsImpl<'_> {
+        HandleRedirectsImpl { pool: &self.0 }
+    }
+
+    pub fn link_previews(&self) -> LinkPreviewsImpl<'_> {
+        LinkPreviewsImpl { pool: &self.0 }
+    }
+
+    pub fn login_attempts(&self) -> LoginAttemptsImpl<'_> {
+        LoginAttemptsImpl { pool: &self.0 }
+    }
+
+    pub fn login_links(&self) -> LoginLinksImpl<'_> {
+        LoginLinksImpl { pool: &self.0 }
+    }
+
+    pub fn password_resets(&self) -> PasswordResetsImpl<'_> {
+        PasswordResetsImpl { pool: &self.0 }
+    }
+
+    pub fn recovery_codes(&self) -> RecoveryCodesImpl<'_> {
+        RecoveryCodesImpl { pool: &self.0 }
+    }
+
+    pub fn saved_searches(&self) -> SavedSearchesImpl<'_> {
+        SavedSearchesImpl { pool: &self.0 }
+    }
+
+    pub fn sessions(&self) -> SessionsImpl<'_> {
+        SessionsImpl { pool: &self.0 }
+    }
 }
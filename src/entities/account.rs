@@ -1,10 +1,27 @@
+use chrono::{DateTime, Utc};
 use sha2::{Digest, Sha256};
 
+use crate::totp;
+
 pub struct Account {
     id: Option<i32>,
     pub email: String,
     pub hashed_password: String,
     pub display_name: String,
+    pub handle: String,
+    pub email_verified: bool,
+    pub totp_secret: Option<String>,
+    pub totp_last_step: Option<i64>,
+    /// Offered while enrolling an authenticator, until a code from it confirms it.
+    pub totp_pending_secret: Option<String>,
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// How long new tweets are held back so they can still be taken back.
+    pub undo_send_seconds: i32,
+    /// Shown at the top of the profile.
+    pub pinned_tweet_id: Option<i32>,
+    /// Shows tweets with a content warning opened instead of collapsed.
+    pub always_expand_content_warnings: bool,
 }
 
 impl Account {
@@ -14,15 +31,35 @@ impl Account {
             email,
             hashed_password,
             display_name,
+            handle: String::new(),
+            email_verified: false,
+            totp_secret: None,
+            totp_last_step: None,
+            totp_pending_secret: None,
+            deletion_scheduled_at: None,
+            deleted_at: None,
+            undo_send_seconds: 0,
+            pinned_tweet_id: None,
+            always_expand_content_warnings: false,
         }
     }
 
-    pub fn create(email: &str, password: &str, display_name: &str) -> Account {
+    pub fn create(email: &str, password: &str, display_name: &str, handle: &str) -> Account {
         Account {
             id: None,
             email: email.to_string(),
             hashed_password: to_sha256(password),
             display_name: display_name.to_string(),
+            handle: handle.to_string(),
+            email_verified: false,
+            totp_secret: None,
+            totp_last_step: None,
+            totp_pending_secret: None,
+            deletion_scheduled_at: None,
+            deleted_at: None,
+            undo_send_seconds: 0,
+            pinned_tweet_id: None,
+            always_expand_content_warnings: false,
         }
     }
 
@@ -30,9 +67,146 @@ impl Account {
         self.id
     }
 
+    /// Handles are 1 to 15 ASCII letters, digits or underscores, and compared case-insensitively.
+    pub fn is_valid_handle(handle: &str) -> bool {
+        !handle.is_empty()
+            && handle.len() <= MAX_HANDLE_LENGTH
+            && handle
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '_')
+    }
+
+    /// Only the shape of the address is checked here. Whether it receives mail is up to the
+    /// verification email.
+    pub fn is_valid_email(email: &str) -> bool {
+        let (local, domain) = match email.rsplit_once('@') {
+            Some(parts) => parts,
+            None => return false,
+        };
+        email.len() <= MAX_EMAIL_LENGTH
+            && !local.is_empty()
+            && !email.chars().any(|x| x.is_whitespace() || x.is_control())
+            && domain.contains('.')
+            && domain.split('.').all(|x| !x.is_empty())
+    }
+
+    pub fn is_valid_undo_send_seconds(seconds: i32) -> bool {
+        (0..=MAX_UNDO_SEND_SECONDS).contains(&seconds)
+    }
+
+    pub fn can_post(&self) -> bool {
+        self.email_verified && !self.is_deleted() && self.deletion_scheduled_at.is_none()
+    }
+
+    pub fn change_email(&mut self, email: &str) {
+        self.email = email.to_string();
+        self.email_verified = false;
+    }
+
+    pub fn verify_email(&mut self) {
+        self.email_verified = true;
+    }
+
+    pub fn two_factor_enabled(&self) -> bool {
+        self.totp_secret.is_some()
+    }
+
+    pub fn disable_two_factor(&mut self) {
+        self.totp_secret = None;
+        self.totp_last_step = None;
+        self.totp_pending_secret = None;
+    }
+
+    /// Switches to the pending secret if the code was made with it. Any secret used before is
+    /// replaced, which is how an authenticator is enrolled again.
+    pub fn confirm_two_factor(&mut self, code: &str, now: DateTime<Utc>) -> bool {
+        let secret = match &self.totp_pending_secret {
+            Some(secret) => secret,
+            None => return false,
+        };
+        match totp::verify(secret, code, now) {
+            Some(step) => {
+                self.totp_secret = self.totp_pending_secret.take();
+                self.totp_last_step = Some(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Accepts each code only once, so an observed code can't be replayed within its window.
+    pub fn verify_totp(&mut self, code: &str, now: DateTime<Utc>) -> bool {
+        let secret = match &self.totp_secret {
+            Some(secret) => secret,
+            None => return false,
+        };
+        match totp::verify(secret, code, now) {
+            Some(step) if self.totp_last_step.map(|x| step > x).unwrap_or(true) => {
+                self.totp_last_step = Some(step);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    pub fn schedule_deletion(&mut self, at: DateTime<Utc>) {
+        self.deletion_scheduled_at = Some(at);
+    }
+
+    pub fn cancel_deletion(&mut self) {
+        self.deletion_scheduled_at = None;
+    }
+
+    /// Turns the account into a tombstone that keeps its id and handle but nothing else that
+    /// identifies the user. The handle stays taken, so old links to the profile keep saying the
+    /// account was deleted. The empty password hash can never match, so the account can't be
+    /// signed into again.
+    pub fn erase(&mut self, now: DateTime<Utc>) {
+        self.email = format!("deleted-{}@invalid", self.id.unwrap_or(0));
+        self.hashed_password = String::new();
+        self.display_name = String::new();
+        self.email_verified = false;
+        self.totp_secret = None;
+        self.totp_last_step = None;
+        self.totp_pending_secret = None;
+        self.deletion_scheduled_at = None;
+        self.deleted_at = Some(now);
+    }
+
+    pub fn set_password(&mut self, password: &str) {
+        self.hashed_password = to_sha256(password);
+    }
+
     pub fn matches_password(&self, password: &str) -> bool {
-        self.hashed_password == to_sha256(password)
+        constant_time_eq(&self.hashed_password, &to_sha256(password))
+    }
+
+    /// Does the same work as `matches_password` for an email that has no account,
+    /// so that the response time doesn't reveal whether the account exists.
+    pub fn simulate_password_check(password: &str) -> bool {
+        constant_time_eq(DUMMY_HASHED_PASSWORD, &to_sha256(password))
+    }
+}
+
+const MAX_HANDLE_LENGTH: usize = 15;
+const MAX_EMAIL_LENGTH: usize = 254;
+const MAX_UNDO_SEND_SECONDS: i32 = 30;
+
+const DUMMY_HASHED_PASSWORD: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
     }
+    a.bytes()
+        .zip(b.bytes())
+        .fold(0, |acc, (x, y)| acc | (x ^ y))
+        == 0
 }
 
 fn to_sha256(str: &str) -> String {
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+
+pub struct Draft {
+    id: Option<i32>,
+    pub account_id: i32,
+    pub message: String,
+    pub content_warning: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Draft {
+    pub fn new(id: i32, account_id: i32, message: String, updated_at: DateTime<Utc>) -> Draft {
+        Draft {
+            id: Some(id),
+            account_id,
+            message,
+            content_warning: None,
+            updated_at,
+        }
+    }
+
+    pub fn create(account_id: i32, message: &str, content_warning: Option<&str>) -> Draft {
+        Draft {
+            id: None,
+            account_id,
+            message: message.to_string(),
+            content_warning: content_warning.map(|x| x.to_string()),
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> Option<i32> {
+        self.id
+    }
+
+    pub fn update(&mut self, message: &str, content_warning: Option<&str>, now: DateTime<Utc>) {
+        self.message = message.to_string();
+        self.content_warning = content_warning.map(|x| x.to_string());
+        self.updated_at = now;
+    }
+}
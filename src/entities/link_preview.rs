@@ -0,0 +1,20 @@
+/// The OpenGraph card of a page linked from a tweet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    pub image_url: Option<String>,
+}
+
+impl LinkPreview {
+    /// What gets cached for a page that couldn't be fetched or has nothing to show.
+    pub fn empty(url: &str) -> LinkPreview {
+        LinkPreview {
+            url: url.to_string(),
+            title: String::new(),
+            description: String::new(),
+            image_url: None,
+        }
+    }
+}
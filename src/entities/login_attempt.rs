@@ -0,0 +1,62 @@
+use chrono::{DateTime, Duration, Utc};
+use std::net::IpAddr;
+
+const FAILURE_WINDOW_SECONDS: i64 = 3600;
+const MAX_BACKOFF_SECONDS: i64 = 300;
+const LOCKOUT_SECONDS: i64 = 900;
+
+pub struct LoginAttempt {
+    pub key: String,
+    pub failures: i32,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+impl LoginAttempt {
+    pub fn new(key: String, failures: i32, last_failed_at: DateTime<Utc>) -> LoginAttempt {
+        LoginAttempt {
+            key,
+            failures,
+            last_failed_at,
+        }
+    }
+
+    pub fn email_key(email: &str) -> String {
+        format!("email:{}", email.trim().to_lowercase())
+    }
+
+    pub fn ip_key(ip: IpAddr) -> String {
+        format!("ip:{}", ip)
+    }
+
+    pub fn two_factor_key(account_id: i32) -> String {
+        format!("two_factor:{}", account_id)
+    }
+
+    /// Failures older than this are forgotten when the next one is recorded.
+    pub fn failure_window_start(now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::seconds(FAILURE_WINDOW_SECONDS)
+    }
+
+    pub fn is_blocked(&self, now: DateTime<Utc>) -> bool {
+        now < self.blocked_until()
+    }
+
+    fn blocked_until(&self) -> DateTime<Utc> {
+        // A single client IP may be shared by many users, so it gets more slack.
+        let (free_attempts, lockout_threshold) = if self.key.starts_with("ip:") {
+            (10, 50)
+        } else {
+            (3, 10)
+        };
+
+        let seconds = if self.failures >= lockout_threshold {
+            LOCKOUT_SECONDS
+        } else if self.failures >= free_attempts {
+            let exponent = (self.failures - free_attempts) as u32;
+            2_i64.saturating_pow(exponent).min(MAX_BACKOFF_SECONDS)
+        } else {
+            0
+        };
+        self.last_failed_at + Duration::seconds(seconds)
+    }
+}
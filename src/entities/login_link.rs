@@ -0,0 +1,38 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::token;
+
+const LOGIN_LINK_TTL_SECONDS: i64 = 900;
+
+pub struct LoginLink {
+    pub account_id: i32,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl LoginLink {
+    pub fn new(
+        account_id: i32,
+        token_hash: String,
+        created_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> LoginLink {
+        LoginLink {
+            account_id,
+            token_hash,
+            created_at,
+            expires_at,
+        }
+    }
+
+    pub fn create(account_id: i32, token: &str) -> LoginLink {
+        let now = Utc::now();
+        LoginLink::new(
+            account_id,
+            token::digest(token),
+            now,
+            now + Duration::seconds(LOGIN_LINK_TTL_SECONDS),
+        )
+    }
+}
@@ -0,0 +1,10 @@
+/// An image attached to a tweet. The files themselves live in the `MediaStore`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Media {
+    pub key: String,
+    pub thumbnail_key: String,
+    pub content_type: String,
+    pub alt_text: String,
+    pub width: i32,
+    pub height: i32,
+}
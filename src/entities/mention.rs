@@ -0,0 +1,6 @@
+/// An account mentioned in a tweet, along with the handle as it was written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mention {
+    pub account_id: i32,
+    pub handle: String,
+}
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::token;
+
+const PASSWORD_RESET_TTL_SECONDS: i64 = 3600;
+
+pub struct PasswordReset {
+    pub account_id: i32,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PasswordReset {
+    pub fn new(account_id: i32, token_hash: String, expires_at: DateTime<Utc>) -> PasswordReset {
+        PasswordReset {
+            account_id,
+            token_hash,
+            expires_at,
+        }
+    }
+
+    pub fn create(account_id: i32, token: &str) -> PasswordReset {
+        PasswordReset::new(
+            account_id,
+            token::digest(token),
+            Utc::now() + Duration::seconds(PASSWORD_RESET_TTL_SECONDS),
+        )
+    }
+}
@@ -0,0 +1,27 @@
+pub struct SavedSearch {
+    id: Option<i32>,
+    pub account_id: i32,
+    pub query: String,
+}
+
+impl SavedSearch {
+    pub fn new(id: i32, account_id: i32, query: String) -> SavedSearch {
+        SavedSearch {
+            id: Some(id),
+            account_id,
+            query,
+        }
+    }
+
+    pub fn create(account_id: i32, query: &str) -> SavedSearch {
+        SavedSearch {
+            id: None,
+            account_id,
+            query: query.to_string(),
+        }
+    }
+
+    pub fn id(&self) -> Option<i32> {
+        self.id
+    }
+}
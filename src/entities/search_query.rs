@@ -0,0 +1,95 @@
+use chrono::NaiveDate;
+
+/// One condition of a search query. A tweet has to satisfy all of them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Clause {
+    /// A word, or a quoted phrase, that has to appear in the message.
+    Contains(String),
+    /// `-word` or `-"phrase"`.
+    Excludes(String),
+    /// `from:handle`
+    From(String),
+    /// `to:handle`, a tweet that mentions the account.
+    To(String),
+    /// `since:2026-01-01`, on or after the day.
+    Since(NaiveDate),
+    /// `until:2026-01-01`, before the day.
+    Until(NaiveDate),
+    /// `has:links`
+    HasLinks,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    pub clauses: Vec<Clause>,
+}
+
+impl SearchQuery {
+    /// Parses Twitter-style operators. Anything that isn't a valid operator is searched as text.
+    pub fn parse(query: &str) -> SearchQuery {
+        let mut clauses = Vec::new();
+        let mut chars = query.chars().peekable();
+        loop {
+            while chars.next_if(|x| x.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let excluded = chars.next_if_eq(&'-').is_some();
+            let (word, quoted) = if chars.next_if_eq(&'"').is_some() {
+                (chars.by_ref().take_while(|x| *x != '"').collect(), true)
+            } else {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|x| !x.is_whitespace()) {
+                    word.push(c);
+                }
+                (word, false)
+            };
+            if word.is_empty() {
+                continue;
+            }
+
+            let clause = if excluded {
+                Clause::Excludes(word)
+            } else if quoted {
+                Clause::Contains(word)
+            } else {
+                operator(&word).unwrap_or(Clause::Contains(word))
+            };
+            clauses.push(clause);
+        }
+        SearchQuery { clauses }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// The words and phrases to look for, which are also what gets highlighted.
+    pub fn terms(&self) -> Vec<String> {
+        self.clauses
+            .iter()
+            .filter_map(|x| match x {
+                Clause::Contains(term) => Some(term.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn operator(word: &str) -> Option<Clause> {
+    let (key, value) = word.split_once(':')?;
+    if value.is_empty() {
+        return None;
+    }
+    let handle = || value.trim_start_matches('@').to_string();
+    let date = || NaiveDate::parse_from_str(value, "%Y-%m-%d").ok();
+    match key.to_lowercase().as_str() {
+        "from" => Some(Clause::From(handle())),
+        "to" => Some(Clause::To(handle())),
+        "since" => date().map(Clause::Since),
+        "until" => date().map(Clause::Until),
+        "has" if value.eq_ignore_ascii_case("links") => Some(Clause::HasLinks),
+        _ => None,
+    }
+}
@@ -1,11 +1,31 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::entities::{LinkPreview, Media, Mention};
 
 pub struct Tweet {
     id: Option<i32>,
     pub message: String,
     pub posted_at: DateTime<Utc>,
     pub posted_by: i32,
-    deleted: bool,
+    /// The previous tweet of the thread.
+    pub in_reply_to: Option<i32>,
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Shown in place of the message, which stays collapsed until the reader opens it.
+    pub content_warning: Option<String>,
+    /// Set while the tweet is scheduled and not visible yet.
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Scheduled only for the author's undo delay, rather than for a time they chose.
+    pub pending: bool,
+    pub mentions: Vec<Mention>,
+    pub media: Vec<Media>,
+    /// Normalized hashtags, only set when creating or editing the tweet.
+    pub tags: Vec<String>,
+    /// URLs in the message, only set when creating or editing the tweet.
+    pub links: Vec<String>,
+    /// The card of the first link whose page has one.
+    pub link_preview: Option<LinkPreview>,
+    /// Deleted tweets stay in the author's trash for a while before being purged.
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Tweet {
@@ -15,7 +35,17 @@ impl Tweet {
             message,
             posted_at,
             posted_by,
-            deleted: false,
+            in_reply_to: None,
+            edited_at: None,
+            content_warning: None,
+            publish_at: None,
+            pending: false,
+            mentions: Vec::new(),
+            media: Vec::new(),
+            tags: Vec::new(),
+            links: Vec::new(),
+            link_preview: None,
+            deleted_at: None,
         }
     }
 
@@ -25,7 +55,17 @@ impl Tweet {
             message: message.into(),
             posted_at: Utc::now(),
             posted_by,
-            deleted: false,
+            in_reply_to: None,
+            edited_at: None,
+            content_warning: None,
+            publish_at: None,
+            pending: false,
+            mentions: Vec::new(),
+            media: Vec::new(),
+            tags: Vec::new(),
+            links: Vec::new(),
+            link_preview: None,
+            deleted_at: None,
         }
     }
 
@@ -33,11 +73,40 @@ impl Tweet {
         self.id
     }
 
-    pub fn is_deleted(&self) -> bool {
-        self.deleted
+    pub fn is_scheduled(&self) -> bool {
+        self.publish_at.is_some()
+    }
+
+    /// Only the author can edit, and only for a while after posting.
+    pub fn is_editable_by(&self, account_id: i32, now: DateTime<Utc>, window: Duration) -> bool {
+        self.posted_by == account_id && now < self.posted_at + window
+    }
+
+    /// Replaces the message. The store keeps the previous one as a revision.
+    pub fn edit(&mut self, message: &str, now: DateTime<Utc>) {
+        self.message = message.into();
+        self.edited_at = Some(now);
+    }
+
+    pub fn delete(&mut self, now: DateTime<Utc>) {
+        self.deleted_at = Some(now);
+    }
+
+    /// Only the author can restore, and only until the tweet is purged.
+    pub fn is_restorable_by(
+        &self,
+        account_id: i32,
+        now: DateTime<Utc>,
+        retention: Duration,
+    ) -> bool {
+        self.posted_by == account_id
+            && self
+                .deleted_at
+                .map(|x| now < x + retention)
+                .unwrap_or(false)
     }
 
-    pub fn delete(&mut self) {
-        self.deleted = true;
+    pub fn restore(&mut self) {
+        self.deleted_at = None;
     }
 }
@@ -0,0 +1,7 @@
+use chrono::{DateTime, Utc};
+
+/// An earlier version of an edited tweet.
+pub struct TweetRevision {
+    pub message: String,
+    pub written_at: DateTime<Utc>,
+}
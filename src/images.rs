@@ -0,0 +1,75 @@
+use image::io::{Limits, Reader};
+use image::{ImageFormat, ImageOutputFormat};
+use std::io::Cursor;
+
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+const MAX_DIMENSION: u32 = 8192;
+const THUMBNAIL_SIZE: u32 = 400;
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug, PartialEq)]
+pub enum ImageError {
+    TooLarge,
+    Unsupported,
+}
+
+pub struct ProcessedImage {
+    pub bytes: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Checks that the upload really is a supported image by decoding it, then encodes it again
+/// from the pixels alone. That drops EXIF metadata such as the GPS position and camera details.
+/// JPEG stays JPEG; PNG, GIF and WebP become PNG (only the first frame of an animation is kept).
+pub fn process(bytes: &[u8]) -> Result<ProcessedImage, ImageError> {
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(ImageError::TooLarge);
+    }
+    let format = image::guess_format(bytes).map_err(|_| ImageError::Unsupported)?;
+    let (output, content_type, extension) = match format {
+        ImageFormat::Jpeg => (ImageOutputFormat::Jpeg(JPEG_QUALITY), "image/jpeg", "jpg"),
+        ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP => {
+            (ImageOutputFormat::Png, "image/png", "png")
+        }
+        _ => return Err(ImageError::Unsupported),
+    };
+
+    // Refuses huge dimensions before allocating the pixels, so a small file can't exhaust memory.
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    let mut reader = Reader::new(Cursor::new(bytes));
+    reader.set_format(format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|_| ImageError::Unsupported)?;
+
+    let encode = |image: &image::DynamicImage| {
+        let mut out = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut out), output.clone())
+            .map(|_| out)
+            .map_err(|_| ImageError::Unsupported)
+    };
+    Ok(ProcessedImage {
+        bytes: encode(&image)?,
+        thumbnail: encode(&image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE))?,
+        content_type,
+        extension,
+        width: image.width() as i32,
+        height: image.height() as i32,
+    })
+}
+
+pub fn content_type(key: &str) -> &'static str {
+    if key.ends_with(".jpg") {
+        "image/jpeg"
+    } else if key.ends_with(".png") {
+        "image/png"
+    } else {
+        "application/octet-stream"
+    }
+}
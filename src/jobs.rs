@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use crate::constants::link_preview_allow_private;
+use crate::database::RepositoryProvider;
+use crate::link_fetcher::HttpLinkFetcher;
+use crate::media_store;
+use crate::services;
+
+pub fn spawn_account_purge(repository_provider: RepositoryProvider) {
+    let media_store = media_store::provider();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            let account_repo = repository_provider.accounts();
+            let tweet_repo = repository_provider.tweets();
+            let session_repo = repository_provider.sessions();
+            services::purge_deleted_accounts(
+                &account_repo,
+                &tweet_repo,
+                &session_repo,
+                &media_store,
+            )
+            .await;
+        }
+    });
+}
+
+pub fn spawn_tweet_purge(repository_provider: RepositoryProvider) {
+    let media_store = media_store::provider();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            let tweet_repo = repository_provider.tweets();
+            services::purge_deleted_tweets(&tweet_repo, &media_store).await;
+        }
+    });
+}
+
+/// Also publishes tweets held for the undo delay whose own timer was lost with a restarted
+/// instance.
+pub fn spawn_scheduled_publish(repository_provider: RepositoryProvider) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(15));
+        loop {
+            interval.tick().await;
+            let tweet_repo = repository_provider.tweets();
+            services::publish_scheduled_tweets(&tweet_repo).await;
+        }
+    });
+}
+
+/// Publishes a tweet held for the undo delay as soon as the delay is over, rather than at the
+/// next run of the scheduled publisher.
+pub fn spawn_pending_publish(repository_provider: RepositoryProvider, at: DateTime<Utc>) {
+    let delay = (at - Utc::now()).to_std().unwrap_or_default();
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        let tweet_repo = repository_provider.tweets();
+        services::publish_scheduled_tweets(&tweet_repo).await;
+    });
+}
+
+pub fn spawn_link_preview_fetch(repository_provider: RepositoryProvider) {
+    let fetcher = HttpLinkFetcher::new(link_preview_allow_private());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(10));
+        loop {
+            interval.tick().await;
+            let link_preview_repo = repository_provider.link_previews();
+            services::fetch_link_previews(&link_preview_repo, &fetcher).await;
+        }
+    });
+}
@@ -1,22 +1,101 @@
 mod constants {
+    use chrono::Duration;
     use std::env;
+    use std::path::PathBuf;
 
     pub const AXUM_SESSION_COOKIE_NAME: &str = "rustwi_session";
     pub const AXUM_SESSION_USER_ID_KEY: &str = "uid";
+    pub const AXUM_SESSION_PENDING_USER_ID_KEY: &str = "pending_uid";
 
     pub fn database_url() -> String {
         dotenv::dotenv().ok();
         env::var("DATABASE_URL").unwrap()
     }
+
+    pub fn secret_key() -> String {
+        dotenv::dotenv().ok();
+        env::var("SECRET_KEY").unwrap()
+    }
+
+    pub fn base_url() -> String {
+        dotenv::dotenv().ok();
+        env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string())
+    }
+
+    /// Days during which a deleted account can still be restored by signing in. 0 deletes at once.
+    pub fn account_deletion_grace_days() -> i64 {
+        dotenv::dotenv().ok();
+        env::var("ACCOUNT_DELETION_GRACE_DAYS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn magic_link_enabled() -> bool {
+        dotenv::dotenv().ok();
+        env::var("MAGIC_LINK_ENABLED")
+            .map(|x| x == "true")
+            .unwrap_or(false)
+    }
+
+    pub fn smtp_url() -> Option<String> {
+        dotenv::dotenv().ok();
+        env::var("SMTP_URL").ok()
+    }
+
+    pub fn mail_from() -> String {
+        dotenv::dotenv().ok();
+        env::var("MAIL_FROM").unwrap_or_else(|_| "Rustwi <noreply@localhost>".to_string())
+    }
+
+    pub fn mail_output_dir() -> PathBuf {
+        dotenv::dotenv().ok();
+        env::var("MAIL_OUTPUT_DIR")
+            .unwrap_or_else(|_| "mail".to_string())
+            .into()
+    }
+
+    /// Lets link previews be fetched from private and loopback addresses, for local testing.
+    pub fn link_preview_allow_private() -> bool {
+        dotenv::dotenv().ok();
+        env::var("LINK_PREVIEW_ALLOW_PRIVATE")
+            .map(|x| x == "true")
+            .unwrap_or(false)
+    }
+
+    /// How long after posting a tweet can be edited, set in minutes. 0 turns editing off.
+    pub fn tweet_edit_window() -> Duration {
+        dotenv::dotenv().ok();
+        let minutes = env::var("TWEET_EDIT_WINDOW_MINUTES")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(30);
+        Duration::minutes(minutes)
+    }
+
+    pub fn media_dir() -> PathBuf {
+        dotenv::dotenv().ok();
+        env::var("MEDIA_DIR")
+            .unwrap_or_else(|_| "media".to_string())
+            .into()
+    }
 }
 
 mod controllers {
     mod accounts;
+    mod drafts;
+    mod media;
     mod root;
+    mod searches;
+    mod settings;
     mod tweets;
 
     pub use accounts::accounts;
+    pub use drafts::drafts;
+    pub use media::media;
     pub use root::app;
+    pub use searches::searches;
+    pub use settings::settings;
     pub use tweets::tweets;
 }
 
@@ -24,62 +103,263 @@ mod database;
 
 mod entities {
     mod account;
+    mod draft;
+    mod link_preview;
+    mod login_attempt;
+    mod login_link;
+    mod media;
+    mod mention;
+    mod password_reset;
+    mod saved_search;
+    mod search_query;
     mod tweet;
+    mod tweet_revision;
 
     pub use account::Account;
+    pub use draft::Draft;
+    pub use link_preview::LinkPreview;
+    pub use login_attempt::LoginAttempt;
+    pub use login_link::LoginLink;
+    pub use media::Media;
+    pub use mention::Mention;
+    pub use password_reset::PasswordReset;
+    pub use saved_search::SavedSearch;
+    pub use search_query::{Clause, SearchQuery};
     pub use tweet::Tweet;
+    pub use tweet_revision::TweetRevision;
+}
+
+mod images;
+
+mod jobs;
+
+mod link_fetcher;
+
+mod mailer;
+
+mod mailers {
+    mod file;
+    mod smtp;
+
+    pub use file::FileMailer;
+    pub use smtp::SmtpMailer;
 }
 
+mod media_store;
+
+mod media_stores {
+    mod local;
+
+    pub use local::LocalMediaStore;
+}
+
+mod opengraph;
+
 mod repos_impl {
     mod accounts;
+    mod drafts;
+    mod handle_redirects;
+    mod link_previews;
+    mod login_attempts;
+    mod login_links;
+    mod password_resets;
+    mod recovery_codes;
+    mod saved_searches;
+    mod sessions;
     mod tweets;
 
     pub use accounts::AccountsImpl;
+    pub use drafts::DraftsImpl;
+    pub use handle_redirects::HandleRedirectsImpl;
+    pub use link_previews::LinkPreviewsImpl;
+    pub use login_attempts::LoginAttemptsImpl;
+    pub use login_links::LoginLinksImpl;
+    pub use password_resets::PasswordResetsImpl;
+    pub use recovery_codes::RecoveryCodesImpl;
+    pub use saved_searches::SavedSearchesImpl;
+    pub use sessions::SessionsImpl;
     pub use tweets::TweetsImpl;
 }
 
 mod repositories {
     mod accounts;
+    mod drafts;
+    mod handle_redirects;
+    mod link_previews;
+    mod login_attempts;
+    mod login_links;
+    mod password_resets;
+    mod recovery_codes;
+    mod saved_searches;
+    mod sessions;
     mod tweets;
 
     pub use accounts::Accounts;
     #[cfg(test)]
     pub use accounts::MockAccounts;
+    pub use drafts::Drafts;
+    #[cfg(test)]
+    pub use drafts::MockDrafts;
+    pub use handle_redirects::HandleRedirects;
+    #[cfg(test)]
+    pub use handle_redirects::MockHandleRedirects;
+    pub use link_previews::LinkPreviews;
+    #[cfg(test)]
+    pub use link_previews::MockLinkPreviews;
+    pub use login_attempts::LoginAttempts;
+    #[cfg(test)]
+    pub use login_attempts::MockLoginAttempts;
+    pub use login_links::LoginLinks;
+    #[cfg(test)]
+    pub use login_links::MockLoginLinks;
+    #[cfg(test)]
+    pub use password_resets::MockPasswordResets;
+    pub use password_resets::PasswordResets;
+    #[cfg(test)]
+    pub use recovery_codes::MockRecoveryCodes;
+    pub use recovery_codes::RecoveryCodes;
+    #[cfg(test)]
+    pub use saved_searches::MockSavedSearches;
+    pub use saved_searches::SavedSearches;
+    #[cfg(test)]
+    pub use sessions::MockSessions;
+    pub use sessions::Sessions;
     #[cfg(test)]
     pub use tweets::MockTweets;
-    pub use tweets::Tweets;
+    pub use tweets::{SearchOrder, Tweets};
 }
 
 mod services {
+    mod account_deletion;
     mod accounts;
+    mod drafts;
+    mod link_previews;
+    mod login_links;
+    mod password_resets;
+    mod profiles;
+    mod scheduled;
+    mod searches;
+    mod settings;
+    mod threads;
+    mod trash;
     mod tweets;
+    mod two_factor;
 
-    pub use accounts::{clear_session, create_account, create_session, SessionToken};
-    pub use tweets::{create_tweet, delete_tweet, list_tweets};
+    pub use account_deletion::{delete_account, purge_deleted_accounts, AccountDeletion};
+    pub use accounts::{
+        clear_session, create_account, create_session, resend_verification_email, verify_email,
+        AccountError, SessionError, SessionToken,
+    };
+    pub use drafts::{
+        discard_draft, find_draft, list_drafts, publish_draft, save_draft, DraftError,
+    };
+    pub use link_previews::fetch_link_previews;
+    pub use login_links::{create_session_from_login_link, request_login_link};
+    pub use password_resets::{request_password_reset, reset_password};
+    pub use profiles::{pin_tweet, profile, unpin_tweet, ProfileLookup};
+    pub use scheduled::{
+        cancel_scheduled_tweet, edit_scheduled_tweet, list_scheduled, parse_publish_at,
+        pending_tweet, publish_scheduled_tweets, scheduled_edit_form,
+    };
+    pub use searches::{
+        delete_saved_search, save_search, search_accounts, search_tweets, suggest_accounts,
+    };
+    pub use settings::{
+        change_content_warning_display, change_display_name, change_email, change_handle,
+        change_password, change_undo_send_delay, settings, SettingsError,
+    };
+    pub use threads::{create_thread, thread};
+    pub use trash::{list_trash, purge_deleted_tweets, restore_tweet};
+    pub use tweets::{
+        create_tweet, delete_tweet, edit_form, edit_tweet, list_mentions, list_tagged, list_tweets,
+        measure_tweet, tweet_history, ImageUpload, NewTweet, Posted, TweetError,
+    };
+    pub use two_factor::{
+        cancel_two_factor_reenrollment, complete_two_factor, disable_two_factor, enable_two_factor,
+        reenroll_two_factor, regenerate_recovery_codes, two_factor_settings,
+    };
 }
 
 mod request;
 
 mod response;
 
+mod text;
+
+mod token;
+
+mod totp;
+
+mod tweet_length;
+
 mod views {
+    mod account_deleted;
+    mod account_search;
+    mod drafts;
     mod home;
+    mod login_link;
+    mod mentions;
+    mod password_forgot;
+    mod password_reset;
+    mod profile;
+    mod scheduled;
+    mod search;
+    mod settings;
     mod sign_in;
     mod sign_up;
+    mod tag;
+    mod thread;
+    mod trash;
+    mod tweet_edit;
+    mod tweet_history;
+    mod tweet_length;
+    mod two_factor_login;
+    mod two_factor_recovery_codes;
+    mod two_factor_settings;
+    mod verification;
     mod partial {
         mod tweet;
 
         pub use tweet::Tweet;
     }
 
-    pub use home::Home;
+    pub use account_deleted::AccountDeleted;
+    pub use account_search::{AccountSearch, AccountSuggestion};
+    pub use drafts::{DraftItem, DraftList, DraftSaved};
+    pub use home::{Home, PendingTweet};
+    pub use login_link::LoginLink;
+    pub use mentions::Mentions;
     pub use partial::Tweet;
+    pub use password_forgot::PasswordForgot;
+    pub use password_reset::PasswordReset;
+    pub use profile::Profile;
+    pub use scheduled::{Scheduled, ScheduledEdit, ScheduledTweet};
+    pub use search::{SavedSearchLink, Search};
+    pub use settings::Settings;
     pub use sign_in::SignIn;
     pub use sign_up::SignUp;
+    pub use tag::Tag;
+    pub use thread::{Thread, ThreadComposer};
+    pub use trash::{DeletedTweet, Trash};
+    pub use tweet_edit::TweetEdit;
+    pub use tweet_history::{Revision, TweetHistory};
+    pub use tweet_length::TweetLength;
+    pub use two_factor_login::TwoFactorLogin;
+    pub use two_factor_recovery_codes::TwoFactorRecoveryCodes;
+    pub use two_factor_settings::TwoFactorSettings;
+    pub use verification::Verification;
 }
 
 pub use controllers::app;
 
+pub async fn setup_jobs() {
+    let repository_provider = database::provider().await;
+    jobs::spawn_account_purge(repository_provider.clone());
+    jobs::spawn_tweet_purge(repository_provider.clone());
+    jobs::spawn_scheduled_publish(repository_provider.clone());
+    jobs::spawn_link_preview_fetch(repository_provider);
+}
+
 pub async fn setup_session_store() {
     let database_url = constants::database_url();
     let store = async_sqlx_session::PostgresSessionStore::new(&database_url)
@@ -0,0 +1,219 @@
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::redirect::Policy;
+use reqwest::Client;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REDIRECTS: usize = 3;
+/// The card is in the `<head>`, so there's no need to read further.
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait LinkFetcher {
+    /// The page's HTML, or None if it isn't an HTML page that can be fetched.
+    async fn fetch(&self, url: &str) -> Option<String>;
+}
+
+/// Fetches pages over HTTP(S) for link previews.
+///
+/// Unless `allow_private` is set, it refuses to connect to loopback, private, link-local and
+/// other non-public addresses, so that a posted URL can't make the server probe its own
+/// network. The check is made on the resolved addresses, so DNS names that point inside are
+/// caught as well, and it applies again to every redirect.
+#[derive(Clone)]
+pub struct HttpLinkFetcher {
+    client: Client,
+    allow_private: bool,
+}
+
+impl HttpLinkFetcher {
+    pub fn new(allow_private: bool) -> HttpLinkFetcher {
+        let client = Client::builder()
+            .timeout(TIMEOUT)
+            .connect_timeout(TIMEOUT)
+            .no_proxy()
+            .user_agent("Rustwi link preview")
+            .redirect(Policy::custom(move |attempt| {
+                if attempt.previous().len() > MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if is_allowed(attempt.url(), allow_private) {
+                    attempt.follow()
+                } else {
+                    attempt.stop()
+                }
+            }))
+            .dns_resolver(Arc::new(PublicResolver { allow_private }))
+            .build()
+            .unwrap();
+        HttpLinkFetcher {
+            client,
+            allow_private,
+        }
+    }
+}
+
+#[axum::async_trait]
+impl LinkFetcher for HttpLinkFetcher {
+    async fn fetch(&self, url: &str) -> Option<String> {
+        let url = Url::parse(url)
+            .ok()
+            .filter(|x| is_allowed(x, self.allow_private))?;
+        let mut response = self
+            .client
+            .get(url)
+            .header(ACCEPT, "text/html")
+            .send()
+            .await
+            .ok()?;
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        // Pages in other encodings would come out garbled, so they get no card.
+        let is_utf8_html = content_type.starts_with("text/html")
+            && content_type
+                .split("charset=")
+                .nth(1)
+                .map(|x| x.trim_matches('"').starts_with("utf-8"))
+                .unwrap_or(true);
+        if !response.status().is_success() || !is_utf8_html {
+            return None;
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.ok()? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY_BYTES {
+                body.truncate(MAX_BODY_BYTES);
+                break;
+            }
+        }
+        Some(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+/// Resolves names like the system does, leaving out the addresses that aren't allowed.
+struct PublicResolver {
+    allow_private: bool,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private = self.allow_private;
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|x| allow_private || is_public(x.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err("no public address".into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Only http(s) URLs, and no addresses written into the URL that the resolver wouldn't allow.
+fn is_allowed(url: &Url, allow_private: bool) -> bool {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return false;
+    }
+    match url.host() {
+        Some(Host::Domain(_)) => true,
+        Some(Host::Ipv4(ip)) => allow_private || is_public(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => allow_private || is_public(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+/// Whether the address is on the public internet, rather than loopback, a private network,
+/// link-local (which includes cloud metadata endpoints), shared or reserved space.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, _, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            // IPv4-mapped and NAT64 addresses lead to an IPv4 address, so that is what counts.
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = ip.octets();
+                return is_public(IpAddr::from([a, b, c, d]));
+            }
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use url::Url;
+
+    fn is_public(ip: &str) -> bool {
+        super::is_public(ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn test_is_public() {
+        assert!(is_public("93.184.216.34"));
+        assert!(is_public("2606:2800:220:1:248:1893:25c8:1946"));
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!is_public(ip), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let allowed =
+            |url: &str, allow_private| super::is_allowed(&Url::parse(url).unwrap(), allow_private);
+        assert!(allowed("https://example.com/", false));
+        assert!(!allowed("ftp://example.com/", false));
+        assert!(!allowed("http://127.0.0.1:8080/", false));
+        assert!(!allowed("http://2130706433/", false));
+        assert!(!allowed("http://[::1]/", false));
+        assert!(allowed("http://127.0.0.1:8080/", true));
+    }
+}
@@ -0,0 +1,40 @@
+use axum::extract::Extension;
+
+use crate::constants::{mail_output_dir, smtp_url};
+use crate::mailers::{FileMailer, SmtpMailer};
+
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait Mailer {
+    async fn send(&self, email: &Email);
+}
+
+pub fn layer() -> Extension<MailerProvider> {
+    let provider = match smtp_url() {
+        Some(url) => MailerProvider::Smtp(SmtpMailer::new(&url)),
+        None => MailerProvider::File(FileMailer::new(mail_output_dir())),
+    };
+    Extension(provider)
+}
+
+#[derive(Clone)]
+pub enum MailerProvider {
+    Smtp(SmtpMailer),
+    File(FileMailer),
+}
+
+#[axum::async_trait]
+impl Mailer for MailerProvider {
+    async fn send(&self, email: &Email) {
+        match self {
+            MailerProvider::Smtp(mailer) => mailer.send(email).await,
+            MailerProvider::File(mailer) => mailer.send(email).await,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use chrono::Utc;
+use std::path::PathBuf;
+
+use crate::constants::mail_from;
+use crate::mailer::{Email, Mailer};
+
+/// Writes each mail to a file instead of delivering it, for running without an SMTP server.
+#[derive(Clone)]
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: PathBuf) -> FileMailer {
+        FileMailer { dir }
+    }
+}
+
+#[axum::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) {
+        let content = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n",
+            mail_from(),
+            email.to,
+            email.subject,
+            email.body
+        );
+        let path = self
+            .dir
+            .join(format!("{}.eml", Utc::now().format("%Y%m%d%H%M%S%f")));
+        let result = async {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(&path, &content).await
+        };
+        match result.await {
+            Ok(()) => tracing::info!("mail to {} written to {}", email.to, path.display()),
+            Err(e) => tracing::error!("failed to write mail to {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::constants::mail_from;
+use crate::mailer::{Email, Mailer};
+
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(url: &str) -> SmtpMailer {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+            .unwrap()
+            .build();
+        SmtpMailer { transport }
+    }
+}
+
+#[axum::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) {
+        let message = Message::builder()
+            .from(mail_from().parse().unwrap())
+            .to(match email.to.parse() {
+                Ok(to) => to,
+                Err(e) => {
+                    tracing::error!("invalid recipient {}: {}", email.to, e);
+                    return;
+                }
+            })
+            .subject(&email.subject)
+            .body(email.body.clone())
+            .unwrap();
+        if let Err(e) = self.transport.send(message).await {
+            tracing::error!("failed to send mail to {}: {}", email.to, e);
+        }
+    }
+}
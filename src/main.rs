@@ -8,13 +8,14 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     rustwi::setup_session_store().await;
+    rustwi::setup_jobs().await;
 
     let app = rustwi::app().await;
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     tracing::debug!("listening on {}", addr);
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr, _>())
         .await
         .unwrap();
 }
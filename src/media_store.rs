@@ -0,0 +1,46 @@
+use axum::extract::Extension;
+
+use crate::constants::media_dir;
+use crate::media_stores::LocalMediaStore;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait MediaStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>);
+    async fn get(&self, key: &str) -> Option<Vec<u8>>;
+    async fn delete(&self, key: &str);
+}
+
+pub fn layer() -> Extension<MediaStoreProvider> {
+    Extension(provider())
+}
+
+pub fn provider() -> MediaStoreProvider {
+    MediaStoreProvider::Local(LocalMediaStore::new(media_dir()))
+}
+
+#[derive(Clone)]
+pub enum MediaStoreProvider {
+    Local(LocalMediaStore),
+}
+
+#[axum::async_trait]
+impl MediaStore for MediaStoreProvider {
+    async fn put(&self, key: &str, bytes: Vec<u8>) {
+        match self {
+            MediaStoreProvider::Local(store) => store.put(key, bytes).await,
+        }
+    }
+
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        match self {
+            MediaStoreProvider::Local(store) => store.get(key).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) {
+        match self {
+            MediaStoreProvider::Local(store) => store.delete(key).await,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use crate::media_store::MediaStore;
+
+/// Keeps uploaded media as files in a directory.
+#[derive(Clone)]
+pub struct LocalMediaStore {
+    dir: PathBuf,
+}
+
+impl LocalMediaStore {
+    pub fn new(dir: PathBuf) -> LocalMediaStore {
+        LocalMediaStore { dir }
+    }
+
+    /// Keys come from URLs, so anything that could leave the directory is refused.
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        let valid = !key.is_empty()
+            && !key.starts_with('.')
+            && key
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '.');
+        if valid {
+            Some(self.dir.join(key))
+        } else {
+            None
+        }
+    }
+}
+
+#[axum::async_trait]
+impl MediaStore for LocalMediaStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) {
+        let path = match self.path(key) {
+            Some(path) => path,
+            None => return,
+        };
+        let result = async {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(&path, bytes).await
+        };
+        if let Err(e) = result.await {
+            tracing::error!("failed to write media to {}: {}", path.display(), e);
+        }
+    }
+
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        tokio::fs::read(self.path(key)?).await.ok()
+    }
+
+    async fn delete(&self, key: &str) {
+        if let Some(path) = self.path(key) {
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                tracing::warn!("failed to delete media {}: {}", path.display(), e);
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use url::Url;
+
+use crate::entities::LinkPreview;
+
+const MAX_TITLE_LENGTH: usize = 120;
+const MAX_DESCRIPTION_LENGTH: usize = 240;
+
+/// Reads the card out of the page's `<head>`: the `og:` properties, falling back to the
+/// Twitter card tags, `<meta name="description">` and `<title>`. A relative image URL is
+/// resolved against the page, and images that aren't http(s) are dropped.
+pub fn parse(url: &str, html: &str) -> LinkPreview {
+    // ASCII lowercasing keeps byte offsets, so positions found in it apply to `html` too.
+    let lower = html.to_ascii_lowercase();
+    let head_end = lower.find("</head").unwrap_or(html.len());
+    let (html, lower) = (&html[..head_end], &lower[..head_end]);
+
+    let mut properties: Vec<(String, String)> = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find("<meta") {
+        let tag_start = offset + start + "<meta".len();
+        let (attributes, len) = attributes(&html[tag_start..]);
+        let key = attributes
+            .iter()
+            .find(|x| x.0 == "property" || x.0 == "name")
+            .map(|x| x.1.to_lowercase());
+        let content = attributes.iter().find(|x| x.0 == "content");
+        if let (Some(key), Some(content)) = (key, content) {
+            properties.push((key, clean(&content.1)));
+        }
+        offset = tag_start + len;
+    }
+    let property = |keys: &[&str]| {
+        keys.iter().find_map(|key| {
+            properties
+                .iter()
+                .find(|x| x.0 == *key && !x.1.is_empty())
+                .map(|x| x.1.clone())
+        })
+    };
+
+    let title = property(&["og:title", "twitter:title"])
+        .or_else(|| title_element(html, lower))
+        .unwrap_or_default();
+    let description =
+        property(&["og:description", "twitter:description", "description"]).unwrap_or_default();
+    let image_url = property(&["og:image", "og:image:url", "twitter:image"])
+        .and_then(|x| Url::parse(url).ok()?.join(&x).ok())
+        .filter(|x| x.scheme() == "http" || x.scheme() == "https")
+        .map(|x| x.to_string());
+    LinkPreview {
+        url: url.to_string(),
+        title: truncate(&title, MAX_TITLE_LENGTH),
+        description: truncate(&description, MAX_DESCRIPTION_LENGTH),
+        image_url,
+    }
+}
+
+fn title_element(html: &str, lower: &str) -> Option<String> {
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    Some(clean(&html[start..end])).filter(|x| !x.is_empty())
+}
+
+/// Parses the attributes of a tag up to its closing `>`, returning them with lowercased names
+/// and how many bytes were read.
+fn attributes(tag: &str) -> (Vec<(String, String)>, usize) {
+    let mut attributes = Vec::new();
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() || rest.starts_with('>') {
+            break;
+        }
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let quoted = &after[1..];
+                    let end = quoted.find(quote).unwrap_or(quoted.len());
+                    value = &quoted[..end];
+                    rest = quoted.get(end + 1..).unwrap_or("");
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    value = &after[..end];
+                    rest = &after[end..];
+                }
+            }
+        }
+        attributes.push((name, value.to_string()));
+    }
+    (attributes, tag.len() - rest.len())
+}
+
+/// Decodes character references and collapses whitespace.
+fn clean(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let reference = rest
+            .find(';')
+            .filter(|x| *x <= 10)
+            .and_then(|end| Some((character(&rest[1..end])?, end)));
+        match reference {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn character(reference: &str) -> Option<char> {
+    match reference {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let number = reference.strip_prefix('#')?;
+            let code = match number.strip_prefix(|c: char| c == 'x' || c == 'X') {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated = text.chars().take(max - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse() {
+        let html = r#"<!DOCTYPE html>
+            <html><head>
+            <title>Fallback</title>
+            <META property="og:title" content="Rust &amp; 東京&#x3002;">
+            <meta content='A  description
+                over lines' property=og:description />
+            <meta property="og:image" content="/images/card.png">
+            </head><body><meta property="og:title" content="Body"></body></html>"#;
+        let preview = super::parse("https://example.com/posts/1", html);
+        assert_eq!(preview.url, "https://example.com/posts/1");
+        assert_eq!(preview.title, "Rust & 東京。");
+        assert_eq!(preview.description, "A description over lines");
+        assert_eq!(
+            preview.image_url,
+            Some("https://example.com/images/card.png".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_fallbacks() {
+        let html = r#"<head><title> Page &lt;1&gt; </title>
+            <meta name="description" content="Plain">
+            <meta property="og:image" content="javascript:alert(1)"></head>"#;
+        let preview = super::parse("https://example.com/", html);
+        assert_eq!(preview.title, "Page <1>");
+        assert_eq!(preview.description, "Plain");
+        assert_eq!(preview.image_url, None);
+
+        let preview = super::parse("https://example.com/", "<p>no head</p>");
+        assert!(preview.title.is_empty());
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(super::truncate("あいう", 3), "あいう");
+        assert_eq!(super::truncate("あいうえ", 3), "あい…");
+    }
+}
@@ -1,7 +1,8 @@
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
 use tokio_postgres::Row;
 
-use crate::database::ConnectionPool;
+use crate::database::{escape_like, ConnectionPool};
 use crate::entities::Account;
 use crate::repositories::Accounts;
 
@@ -46,24 +47,120 @@ impl<'a> Accounts for AccountsImpl<'a> {
         row.map(|r| r.into())
     }
 
+    async fn find_by_id(&self, id: i32) -> Option<Account> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt("SELECT * FROM accounts WHERE id = $1", &[&id])
+            .await
+            .unwrap();
+        row.map(|r| r.into())
+    }
+
+    async fn find_by_handle(&self, handle: &str) -> Option<Account> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "SELECT * FROM accounts WHERE LOWER(handle) = LOWER($1)",
+                &[&handle],
+            )
+            .await
+            .unwrap();
+        row.map(|r| r.into())
+    }
+
+    async fn search(&self, prefix: &str, limit: i64) -> Vec<Account> {
+        let conn = self.pool.get().await.unwrap();
+        let pattern = format!("{}%", escape_like(prefix));
+        let rows = conn
+            .query(
+                "SELECT * FROM accounts
+                 WHERE deleted_at IS NULL
+                   AND (LOWER(handle) LIKE $1 OR LOWER(NORMALIZE(display_name, NFKC)) LIKE $1)
+                 ORDER BY LOWER(handle) LIKE $1 DESC, LOWER(handle)
+                 LIMIT $2",
+                &[&pattern, &limit],
+            )
+            .await
+            .unwrap();
+        rows.into_iter().map(|r| r.into()).collect()
+    }
+
+    async fn list_deletion_due(&self, now: DateTime<Utc>) -> Vec<Account> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT * FROM accounts WHERE deletion_scheduled_at <= $1 AND deleted_at IS NULL",
+                &[&now],
+            )
+            .await
+            .unwrap();
+        rows.into_iter().map(|r| r.into()).collect()
+    }
+
     async fn store(&self, entity: &Account) {
         let conn = self.pool.get().await.unwrap();
-        conn.execute(
-            "INSERT INTO accounts (email, password, display_name) VALUES ($1, $2, $3)",
-            &[&entity.email, &entity.hashed_password, &entity.display_name],
-        )
-        .await
-        .ok();
+        if let Some(id) = entity.id() {
+            conn.execute(
+                "UPDATE accounts SET email = $2, password = $3, display_name = $4, handle = $5,
+                 email_verified = $6, totp_secret = $7, totp_last_step = $8,
+                 deletion_scheduled_at = $9, deleted_at = $10, undo_send_seconds = $11,
+                 pinned_tweet_id = $12, always_expand_content_warnings = $13,
+                 totp_pending_secret = $14
+                 WHERE id = $1",
+                &[
+                    &id,
+                    &entity.email,
+                    &entity.hashed_password,
+                    &entity.display_name,
+                    &entity.handle,
+                    &entity.email_verified,
+                    &entity.totp_secret,
+                    &entity.totp_last_step,
+                    &entity.deletion_scheduled_at,
+                    &entity.deleted_at,
+                    &entity.undo_send_seconds,
+                    &entity.pinned_tweet_id,
+                    &entity.always_expand_content_warnings,
+                    &entity.totp_pending_secret,
+                ],
+            )
+            .await
+            .ok();
+        } else {
+            conn.execute(
+                "INSERT INTO accounts (email, password, display_name, handle, email_verified) VALUES ($1, $2, $3, $4, $5)",
+                &[
+                    &entity.email,
+                    &entity.hashed_password,
+                    &entity.display_name,
+                    &entity.handle,
+                    &entity.email_verified,
+                ],
+            )
+            .await
+            .ok();
+        }
     }
 }
 
 impl From<Row> for Account {
     fn from(r: Row) -> Self {
-        Account::new(
+        let mut account = Account::new(
             r.get("id"),
             r.get("email"),
             r.get("password"),
             r.get("display_name"),
-        )
+        );
+        account.handle = r.get("handle");
+        account.email_verified = r.get("email_verified");
+        account.totp_secret = r.get("totp_secret");
+        account.totp_last_step = r.get("totp_last_step");
+        account.totp_pending_secret = r.get("totp_pending_secret");
+        account.deletion_scheduled_at = r.get("deletion_scheduled_at");
+        account.deleted_at = r.get("deleted_at");
+        account.undo_send_seconds = r.get("undo_send_seconds");
+        account.pinned_tweet_id = r.get("pinned_tweet_id");
+        account.always_expand_content_warnings = r.get("always_expand_content_warnings");
+        account
     }
 }
@@ -0,0 +1,94 @@
+use tokio_postgres::Row;
+
+use crate::database::ConnectionPool;
+use crate::entities::Draft;
+use crate::repositories::Drafts;
+
+pub struct DraftsImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> Drafts for DraftsImpl<'a> {
+    async fn find(&self, id: i32, account_id: i32) -> Option<Draft> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "SELECT * FROM drafts WHERE id = $1 AND account_id = $2",
+                &[&id, &account_id],
+            )
+            .await
+            .unwrap();
+        row.map(|r| r.into())
+    }
+
+    async fn list(&self, account_id: i32) -> Vec<Draft> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT * FROM drafts WHERE account_id = $1 ORDER BY updated_at DESC",
+                &[&account_id],
+            )
+            .await
+            .unwrap();
+        rows.into_iter().map(|r| r.into()).collect()
+    }
+
+    async fn store(&self, entity: &Draft) -> i32 {
+        let conn = self.pool.get().await.unwrap();
+        if let Some(id) = entity.id() {
+            conn.execute(
+                "UPDATE drafts SET message = $3, content_warning = $4, updated_at = $5
+                 WHERE id = $1 AND account_id = $2",
+                &[
+                    &id,
+                    &entity.account_id,
+                    &entity.message,
+                    &entity.content_warning,
+                    &entity.updated_at,
+                ],
+            )
+            .await
+            .unwrap();
+            id
+        } else {
+            let row = conn
+                .query_one(
+                    "INSERT INTO drafts (account_id, message, content_warning, updated_at)
+                     VALUES ($1, $2, $3, $4) RETURNING id",
+                    &[
+                        &entity.account_id,
+                        &entity.message,
+                        &entity.content_warning,
+                        &entity.updated_at,
+                    ],
+                )
+                .await
+                .unwrap();
+            row.get("id")
+        }
+    }
+
+    async fn delete(&self, id: i32, account_id: i32) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "DELETE FROM drafts WHERE id = $1 AND account_id = $2",
+            &[&id, &account_id],
+        )
+        .await
+        .ok();
+    }
+}
+
+impl From<Row> for Draft {
+    fn from(r: Row) -> Self {
+        let mut draft = Draft::new(
+            r.get("id"),
+            r.get("account_id"),
+            r.get("message"),
+            r.get("updated_at"),
+        );
+        draft.content_warning = r.get("content_warning");
+        draft
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+
+use crate::database::ConnectionPool;
+use crate::repositories::HandleRedirects;
+
+pub struct HandleRedirectsImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> HandleRedirects for HandleRedirectsImpl<'a> {
+    async fn find(&self, handle: &str, now: DateTime<Utc>) -> Option<i32> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "SELECT account_id FROM handle_redirects WHERE handle = LOWER($1) AND expires_at > $2",
+                &[&handle, &now],
+            )
+            .await
+            .unwrap();
+        row.map(|r| r.get("account_id"))
+    }
+
+    async fn store(&self, handle: &str, account_id: i32, expires_at: DateTime<Utc>) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "INSERT INTO handle_redirects (handle, account_id, expires_at) VALUES (LOWER($1), $2, $3)
+             ON CONFLICT (handle) DO UPDATE SET account_id = $2, expires_at = $3",
+            &[&handle, &account_id, &expires_at],
+        )
+        .await
+        .ok();
+    }
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+
+use crate::database::ConnectionPool;
+use crate::entities::LinkPreview;
+use crate::repositories::LinkPreviews;
+
+pub struct LinkPreviewsImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> LinkPreviews for LinkPreviewsImpl<'a> {
+    async fn list_pending(&self, limit: i64) -> Vec<String> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT url FROM link_previews WHERE fetched_at IS NULL LIMIT $1",
+                &[&limit],
+            )
+            .await
+            .unwrap();
+        rows.into_iter().map(|r| r.get("url")).collect()
+    }
+
+    async fn store(&self, entity: &LinkPreview, fetched_at: DateTime<Utc>) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "UPDATE link_previews SET title = $2, description = $3, image_url = $4, fetched_at = $5
+             WHERE url = $1",
+            &[
+                &entity.url,
+                &entity.title,
+                &entity.description,
+                &entity.image_url,
+                &fetched_at,
+            ],
+        )
+        .await
+        .ok();
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use tokio_postgres::Row;
+
+use crate::database::ConnectionPool;
+use crate::entities::LoginAttempt;
+use crate::repositories::LoginAttempts;
+
+pub struct LoginAttemptsImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> LoginAttempts for LoginAttemptsImpl<'a> {
+    async fn find_by(&self, key: &str) -> Option<LoginAttempt> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt("SELECT * FROM login_attempts WHERE key = $1", &[&key])
+            .await
+            .unwrap();
+        row.map(|r| r.into())
+    }
+
+    async fn record_failure(&self, key: &str, now: DateTime<Utc>) {
+        let conn = self.pool.get().await.unwrap();
+        let window_start = LoginAttempt::failure_window_start(now);
+        conn.execute(
+            "INSERT INTO login_attempts (key, failures, last_failed_at) VALUES ($1, 1, $2)
+             ON CONFLICT (key) DO UPDATE SET
+               failures = CASE WHEN login_attempts.last_failed_at < $3 THEN 1 ELSE login_attempts.failures + 1 END,
+               last_failed_at = $2",
+            &[&key, &now, &window_start],
+        )
+        .await
+        .ok();
+    }
+
+    async fn reset(&self, key: &str) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute("DELETE FROM login_attempts WHERE key = $1", &[&key])
+            .await
+            .ok();
+    }
+}
+
+impl From<Row> for LoginAttempt {
+    fn from(r: Row) -> Self {
+        LoginAttempt::new(r.get("key"), r.get("failures"), r.get("last_failed_at"))
+    }
+}
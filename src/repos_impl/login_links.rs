@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use tokio_postgres::Row;
+
+use crate::database::ConnectionPool;
+use crate::entities::LoginLink;
+use crate::repositories::LoginLinks;
+
+pub struct LoginLinksImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> LoginLinks for LoginLinksImpl<'a> {
+    async fn count_since(&self, account_id: i32, since: DateTime<Utc>) -> i64 {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_one(
+                "SELECT COUNT(*) FROM login_links WHERE account_id = $1 AND created_at >= $2",
+                &[&account_id, &since],
+            )
+            .await
+            .unwrap();
+        row.get(0)
+    }
+
+    async fn consume(&self, token_hash: &str, now: DateTime<Utc>) -> Option<LoginLink> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "UPDATE login_links SET used_at = $2
+                 WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+                 RETURNING *",
+                &[&token_hash, &now],
+            )
+            .await
+            .unwrap();
+        row.map(|r| r.into())
+    }
+
+    async fn store(&self, entity: &LoginLink) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "INSERT INTO login_links (account_id, token_hash, created_at, expires_at) VALUES ($1, $2, $3, $4)",
+            &[
+                &entity.account_id,
+                &entity.token_hash,
+                &entity.created_at,
+                &entity.expires_at,
+            ],
+        )
+        .await
+        .ok();
+    }
+}
+
+impl From<Row> for LoginLink {
+    fn from(r: Row) -> Self {
+        LoginLink::new(
+            r.get("account_id"),
+            r.get("token_hash"),
+            r.get("created_at"),
+            r.get("expires_at"),
+        )
+    }
+}
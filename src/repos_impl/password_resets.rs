@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use tokio_postgres::Row;
+
+use crate::database::ConnectionPool;
+use crate::entities::PasswordReset;
+use crate::repositories::PasswordResets;
+
+pub struct PasswordResetsImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> PasswordResets for PasswordResetsImpl<'a> {
+    async fn consume(&self, token_hash: &str, now: DateTime<Utc>) -> Option<PasswordReset> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "UPDATE password_resets SET used_at = $2
+                 WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+                 RETURNING *",
+                &[&token_hash, &now],
+            )
+            .await
+            .unwrap();
+        row.map(|r| r.into())
+    }
+
+    async fn store(&self, entity: &PasswordReset) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "INSERT INTO password_resets (account_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+            &[&entity.account_id, &entity.token_hash, &entity.expires_at],
+        )
+        .await
+        .ok();
+    }
+}
+
+impl From<Row> for PasswordReset {
+    fn from(r: Row) -> Self {
+        PasswordReset::new(
+            r.get("account_id"),
+            r.get("token_hash"),
+            r.get("expires_at"),
+        )
+    }
+}
@@ -0,0 +1,44 @@
+use crate::database::ConnectionPool;
+use crate::repositories::RecoveryCodes;
+
+pub struct RecoveryCodesImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> RecoveryCodes for RecoveryCodesImpl<'a> {
+    async fn replace(&self, account_id: i32, code_hashes: Vec<String>) {
+        let mut conn = self.pool.get().await.unwrap();
+        let transaction = conn.transaction().await.unwrap();
+        transaction
+            .execute(
+                "DELETE FROM recovery_codes WHERE account_id = $1",
+                &[&account_id],
+            )
+            .await
+            .unwrap();
+        for code_hash in code_hashes.iter() {
+            transaction
+                .execute(
+                    "INSERT INTO recovery_codes (account_id, code_hash) VALUES ($1, $2)",
+                    &[&account_id, code_hash],
+                )
+                .await
+                .unwrap();
+        }
+        transaction.commit().await.unwrap();
+    }
+
+    async fn consume(&self, account_id: i32, code_hash: &str) -> bool {
+        let conn = self.pool.get().await.unwrap();
+        let updated = conn
+            .execute(
+                "UPDATE recovery_codes SET used_at = now()
+                 WHERE account_id = $1 AND code_hash = $2 AND used_at IS NULL",
+                &[&account_id, &code_hash],
+            )
+            .await
+            .unwrap();
+        updated > 0
+    }
+}
@@ -0,0 +1,51 @@
+use tokio_postgres::Row;
+
+use crate::database::ConnectionPool;
+use crate::entities::SavedSearch;
+use crate::repositories::SavedSearches;
+
+pub struct SavedSearchesImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> SavedSearches for SavedSearchesImpl<'a> {
+    async fn list(&self, account_id: i32) -> Vec<SavedSearch> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT * FROM saved_searches WHERE account_id = $1 ORDER BY created_at DESC",
+                &[&account_id],
+            )
+            .await
+            .unwrap();
+        rows.into_iter().map(|r| r.into()).collect()
+    }
+
+    async fn store(&self, entity: &SavedSearch) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "INSERT INTO saved_searches (account_id, query) VALUES ($1, $2)
+             ON CONFLICT (account_id, query) DO NOTHING",
+            &[&entity.account_id, &entity.query],
+        )
+        .await
+        .ok();
+    }
+
+    async fn delete(&self, id: i32, account_id: i32) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "DELETE FROM saved_searches WHERE id = $1 AND account_id = $2",
+            &[&id, &account_id],
+        )
+        .await
+        .ok();
+    }
+}
+
+impl From<Row> for SavedSearch {
+    fn from(r: Row) -> Self {
+        SavedSearch::new(r.get("id"), r.get("account_id"), r.get("query"))
+    }
+}
@@ -0,0 +1,23 @@
+use crate::database::ConnectionPool;
+use crate::repositories::Sessions;
+
+/// Works directly on the table maintained by `async_sqlx_session::PostgresSessionStore`,
+/// which has no way to look sessions up by their contents.
+pub struct SessionsImpl<'a> {
+    pub pool: &'a ConnectionPool,
+}
+
+#[axum::async_trait]
+impl<'a> Sessions for SessionsImpl<'a> {
+    async fn delete_all(&self, account_id: i32) {
+        let conn = self.pool.get().await.unwrap();
+        conn.execute(
+            "DELETE FROM async_sessions
+             WHERE session::json -> 'data' ->> 'uid' = $1
+                OR session::json -> 'data' ->> 'pending_uid' = $1",
+            &[&account_id.to_string()],
+        )
+        .await
+        .ok();
+    }
+}
@@ -1,8 +1,15 @@
-use tokio_postgres::Row;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use std::collections::HashMap;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, Row, Transaction};
 
-use crate::database::ConnectionPool;
-use crate::entities::Tweet;
-use crate::repositories::Tweets;
+use crate::database::{escape_like, ConnectionPool};
+use crate::entities::{Clause, LinkPreview, Media, Mention, SearchQuery, Tweet, TweetRevision};
+use crate::repositories::{SearchOrder, Tweets};
+
+const SEARCH_LIMIT: i64 = 50;
+/// A cached preview older than this is fetched again when the URL is posted again.
+const LINK_PREVIEW_TTL_DAYS: i32 = 7;
 
 pub struct TweetsImpl<'a> {
     pub pool: &'a ConnectionPool,
@@ -13,47 +20,601 @@ impl<'a> Tweets for TweetsImpl<'a> {
     async fn find(&self, id: i32) -> Option<Tweet> {
         let conn = self.pool.get().await.unwrap();
         let row = conn
-            .query_opt("SELECT * FROM tweets WHERE id = $1", &[&id])
+            .query_opt(
+                "SELECT * FROM tweets WHERE id = $1 AND deleted_at IS NULL AND publish_at IS NULL",
+                &[&id],
+            )
             .await
             .unwrap();
-        row.map(|r| r.into())
+        let tweets = row.map(|r| vec![r.into()]).unwrap_or_default();
+        with_details(&conn, tweets).await.pop()
     }
 
     async fn list(&self) -> Vec<Tweet> {
         let conn = self.pool.get().await.unwrap();
         let rows = conn
-            .query("SELECT * FROM tweets ORDER BY posted_at DESC", &[])
+            .query(
+                "SELECT * FROM tweets WHERE deleted_at IS NULL AND publish_at IS NULL
+                 ORDER BY posted_at DESC",
+                &[],
+            )
+            .await
+            .unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn list_by(&self, account_id: i32) -> Vec<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT * FROM tweets
+                 WHERE posted_by = $1 AND deleted_at IS NULL AND publish_at IS NULL
+                 ORDER BY posted_at DESC",
+                &[&account_id],
+            )
+            .await
+            .unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn list_mentioning(&self, account_id: i32) -> Vec<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT tweets.* FROM tweets JOIN mentions ON mentions.tweet_id = tweets.id
+                 WHERE mentions.account_id = $1
+                 AND tweets.deleted_at IS NULL AND tweets.publish_at IS NULL
+                 ORDER BY tweets.posted_at DESC",
+                &[&account_id],
+            )
+            .await
+            .unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn list_tagged(&self, tag: &str) -> Vec<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT tweets.* FROM tweets
+                 JOIN tweet_tags ON tweet_tags.tweet_id = tweets.id
+                 JOIN tags ON tags.id = tweet_tags.tag_id
+                 WHERE tags.name = $1 AND tweets.deleted_at IS NULL AND tweets.publish_at IS NULL
+                 ORDER BY tweets.posted_at DESC",
+                &[&tag],
+            )
+            .await
+            .unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn search(&self, query: SearchQuery, order: SearchOrder) -> Vec<Tweet> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let conn = self.pool.get().await.unwrap();
+        let (conditions, mut params) = compile_search(&query);
+        let relevance_query = query.terms().join(" ");
+        let order_by = match order {
+            SearchOrder::Relevance if !relevance_query.is_empty() => {
+                params.push(Box::new(relevance_query));
+                format!(
+                    "word_similarity(${}, message) DESC, posted_at DESC",
+                    params.len()
+                )
+            }
+            _ => "posted_at DESC".to_string(),
+        };
+        params.push(Box::new(SEARCH_LIMIT));
+        let sql = format!(
+            "SELECT * FROM tweets WHERE deleted_at IS NULL AND publish_at IS NULL AND {}
+             ORDER BY {} LIMIT ${}",
+            conditions.join(" AND "),
+            order_by,
+            params.len()
+        );
+        let params = params
+            .iter()
+            .map(|x| x.as_ref() as &(dyn ToSql + Sync))
+            .collect::<Vec<_>>();
+        let rows = conn.query(&sql, &params).await.unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn list_thread(&self, id: i32) -> Vec<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        // Walks up to the first tweet of the thread, then down through every reply to it.
+        let rows = conn
+            .query(
+                "WITH RECURSIVE ancestors AS (
+                     SELECT id, in_reply_to FROM tweets WHERE id = $1
+                     UNION ALL
+                     SELECT tweets.id, tweets.in_reply_to FROM tweets
+                     JOIN ancestors ON tweets.id = ancestors.in_reply_to
+                 ), thread AS (
+                     SELECT id FROM ancestors WHERE in_reply_to IS NULL
+                     UNION ALL
+                     SELECT tweets.id FROM tweets JOIN thread ON tweets.in_reply_to = thread.id
+                 )
+                 SELECT tweets.* FROM tweets JOIN thread ON thread.id = tweets.id
+                 WHERE tweets.deleted_at IS NULL AND tweets.publish_at IS NULL
+                 ORDER BY tweets.posted_at, tweets.id",
+                &[&id],
+            )
+            .await
+            .unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn list_revisions(&self, id: i32) -> Vec<TweetRevision> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT * FROM tweet_revisions WHERE tweet_id = $1 ORDER BY written_at DESC",
+                &[&id],
+            )
+            .await
+            .unwrap();
+        rows.into_iter()
+            .map(|r| TweetRevision {
+                message: r.get("message"),
+                written_at: r.get("written_at"),
+            })
+            .collect()
+    }
+
+    async fn find_deleted(&self, id: i32) -> Option<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "SELECT * FROM tweets WHERE id = $1 AND deleted_at IS NOT NULL",
+                &[&id],
+            )
+            .await
+            .unwrap();
+        let tweets = row.map(|r| vec![r.into()]).unwrap_or_default();
+        with_details(&conn, tweets).await.pop()
+    }
+
+    async fn list_deleted_by(&self, account_id: i32, since: DateTime<Utc>) -> Vec<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT * FROM tweets WHERE posted_by = $1 AND deleted_at > $2
+                 ORDER BY deleted_at DESC",
+                &[&account_id, &since],
+            )
+            .await
+            .unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Vec<Media> {
+        let mut conn = self.pool.get().await.unwrap();
+        let transaction = conn.transaction().await.unwrap();
+        let rows = transaction
+            .query(
+                "SELECT id FROM tweets WHERE deleted_at < $1 FOR UPDATE SKIP LOCKED",
+                &[&before],
+            )
+            .await
+            .unwrap();
+        let ids = rows.iter().map(|r| r.get("id")).collect::<Vec<i32>>();
+        // The media rows go with the tweets, so they are read first.
+        let media = transaction
+            .query("SELECT * FROM media WHERE tweet_id = ANY($1)", &[&ids])
+            .await
+            .unwrap();
+        transaction
+            .execute("DELETE FROM tweets WHERE id = ANY($1)", &[&ids])
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+        media.into_iter().map(media_from_row).collect()
+    }
+
+    async fn find_scheduled(&self, id: i32) -> Option<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "SELECT * FROM tweets
+                 WHERE id = $1 AND deleted_at IS NULL AND publish_at IS NOT NULL",
+                &[&id],
+            )
+            .await
+            .unwrap();
+        let tweets = row.map(|r| vec![r.into()]).unwrap_or_default();
+        with_details(&conn, tweets).await.pop()
+    }
+
+    async fn list_scheduled_by(&self, account_id: i32) -> Vec<Tweet> {
+        let conn = self.pool.get().await.unwrap();
+        let rows = conn
+            .query(
+                "SELECT * FROM tweets
+                 WHERE posted_by = $1 AND deleted_at IS NULL AND publish_at IS NOT NULL
+                 AND NOT pending
+                 ORDER BY publish_at",
+                &[&account_id],
+            )
+            .await
+            .unwrap();
+        with_details(&conn, rows.into_iter().map(|r| r.into()).collect()).await
+    }
+
+    async fn cancel_scheduled(&self, id: i32, account_id: i32, now: DateTime<Utc>) -> bool {
+        let conn = self.pool.get().await.unwrap();
+        let row = conn
+            .query_opt(
+                "UPDATE tweets SET deleted_at = $3
+                 WHERE id = $1 AND posted_by = $2 AND deleted_at IS NULL AND publish_at IS NOT NULL
+                 RETURNING id",
+                &[&id, &account_id, &now],
+            )
             .await
             .unwrap();
-        rows.into_iter().map(|r| r.into()).collect()
+        row.is_some()
     }
 
-    async fn store(&self, entity: &Tweet) {
+    async fn publish_due(&self, now: DateTime<Utc>) -> u64 {
         let conn = self.pool.get().await.unwrap();
+        // A single statement, so a tweet another instance is publishing is either skipped or no
+        // longer due by the time its lock is released.
+        conn.execute(
+            "UPDATE tweets SET posted_at = $1, publish_at = NULL, pending = FALSE
+             WHERE id IN (
+                 SELECT id FROM tweets
+                 WHERE publish_at <= $1 AND deleted_at IS NULL
+                 FOR UPDATE SKIP LOCKED
+             ) AND publish_at <= $1",
+            &[&now],
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn store(&self, entity: &Tweet) -> i32 {
+        let mut conn = self.pool.get().await.unwrap();
         if let Some(id) = entity.id() {
-            if entity.is_deleted() {
-                conn.execute("DELETE FROM tweets WHERE id = $1", &[&id])
+            let transaction = conn.transaction().await.unwrap();
+            let row = transaction
+                .query_one(
+                    "SELECT message, publish_at FROM tweets WHERE id = $1 FOR UPDATE",
+                    &[&id],
+                )
+                .await
+                .unwrap();
+            let published = row.get::<_, Option<DateTime<Utc>>>("publish_at").is_none();
+            if published && entity.is_scheduled() {
+                // Published in the meantime, so it can't be changed as a scheduled tweet anymore.
+                return id;
+            }
+            let changed = row.get::<_, String>("message") != entity.message;
+            // Keeps the current version of a published tweet as a revision.
+            if changed && published {
+                transaction
+                    .execute(
+                        "INSERT INTO tweet_revisions (tweet_id, message, written_at)
+                         SELECT id, message, COALESCE(edited_at, posted_at) FROM tweets
+                         WHERE id = $1",
+                        &[&id],
+                    )
                     .await
-                    .ok();
+                    .unwrap();
             }
+            transaction
+                .execute(
+                    "UPDATE tweets SET message = $2, edited_at = $3, deleted_at = $4, publish_at = $5,
+                     content_warning = $6
+                     WHERE id = $1",
+                    &[
+                        &id,
+                        &entity.message,
+                        &entity.edited_at,
+                        &entity.deleted_at,
+                        &entity.publish_at,
+                        &entity.content_warning,
+                    ],
+                )
+                .await
+                .unwrap();
+            if changed {
+                for table in ["mentions", "tweet_tags", "tweet_links"] {
+                    transaction
+                        .execute(
+                            format!("DELETE FROM {} WHERE tweet_id = $1", table).as_str(),
+                            &[&id],
+                        )
+                        .await
+                        .unwrap();
+                }
+                insert_details(&transaction, id, entity).await;
+            }
+            transaction.commit().await.unwrap();
+            id
         } else {
-            conn.execute(
-                "INSERT INTO tweets (message, posted_at, posted_by) VALUES ($1, $2, $3)",
-                &[&entity.message, &entity.posted_at, &entity.posted_by],
+            let transaction = conn.transaction().await.unwrap();
+            let id = insert(&transaction, entity, entity.in_reply_to).await;
+            transaction.commit().await.unwrap();
+            id
+        }
+    }
+
+    async fn store_thread(&self, entities: &[Tweet]) {
+        let mut conn = self.pool.get().await.unwrap();
+        let transaction = conn.transaction().await.unwrap();
+        let mut previous = entities.first().and_then(|x| x.in_reply_to);
+        for entity in entities {
+            previous = Some(insert(&transaction, entity, previous).await);
+        }
+        transaction.commit().await.unwrap();
+    }
+
+    async fn delete_all_by(&self, account_id: i32) -> Vec<Media> {
+        let mut conn = self.pool.get().await.unwrap();
+        let transaction = conn.transaction().await.unwrap();
+        // The media rows go with the tweets, so they are read first.
+        let media = transaction
+            .query(
+                "SELECT media.* FROM media JOIN tweets ON media.tweet_id = tweets.id
+                 WHERE tweets.posted_by = $1",
+                &[&account_id],
             )
             .await
-            .ok();
+            .unwrap();
+        transaction
+            .execute("DELETE FROM tweets WHERE posted_by = $1", &[&account_id])
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+        media.into_iter().map(media_from_row).collect()
+    }
+}
+
+/// Inserts a new tweet with its media and details, returning its id.
+async fn insert(transaction: &Transaction<'_>, entity: &Tweet, in_reply_to: Option<i32>) -> i32 {
+    let row = transaction
+        .query_one(
+            "INSERT INTO tweets
+             (message, posted_at, posted_by, publish_at, pending, in_reply_to, content_warning)
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+            &[
+                &entity.message,
+                &entity.posted_at,
+                &entity.posted_by,
+                &entity.publish_at,
+                &entity.pending,
+                &in_reply_to,
+                &entity.content_warning,
+            ],
+        )
+        .await
+        .unwrap();
+    let id: i32 = row.get("id");
+    for (position, media) in entity.media.iter().enumerate() {
+        transaction
+            .execute(
+                "INSERT INTO media
+                 (tweet_id, position, key, thumbnail_key, content_type, alt_text, width, height)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[
+                    &id,
+                    &(position as i32),
+                    &media.key,
+                    &media.thumbnail_key,
+                    &media.content_type,
+                    &media.alt_text,
+                    &media.width,
+                    &media.height,
+                ],
+            )
+            .await
+            .unwrap();
+    }
+    insert_details(transaction, id, entity).await;
+    id
+}
+
+/// Inserts what is derived from the message: mentions, hashtags and links.
+async fn insert_details(transaction: &Transaction<'_>, id: i32, entity: &Tweet) {
+    for mention in entity.mentions.iter() {
+        transaction
+            .execute(
+                "INSERT INTO mentions (tweet_id, account_id, handle) VALUES ($1, $2, $3)",
+                &[&id, &mention.account_id, &mention.handle],
+            )
+            .await
+            .unwrap();
+    }
+    for tag in entity.tags.iter() {
+        let row = transaction
+            .query_one(
+                "INSERT INTO tags (name) VALUES ($1)
+                 ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name RETURNING id",
+                &[tag],
+            )
+            .await
+            .unwrap();
+        let tag_id: i32 = row.get("id");
+        transaction
+            .execute(
+                "INSERT INTO tweet_tags (tweet_id, tag_id) VALUES ($1, $2)",
+                &[&id, &tag_id],
+            )
+            .await
+            .unwrap();
+    }
+    for (position, url) in entity.links.iter().enumerate() {
+        transaction
+            .execute(
+                "INSERT INTO tweet_links (tweet_id, position, url) VALUES ($1, $2, $3)",
+                &[&id, &(position as i32), url],
+            )
+            .await
+            .unwrap();
+        transaction
+            .execute(
+                "INSERT INTO link_previews (url) VALUES ($1)
+                 ON CONFLICT (url) DO UPDATE SET fetched_at = NULL
+                 WHERE link_previews.fetched_at < NOW() - make_interval(days => $2)",
+                &[url, &LINK_PREVIEW_TTL_DAYS],
+            )
+            .await
+            .unwrap();
+    }
+}
+
+/// Loads the mentions, media and link previews of the tweets.
+async fn with_details(conn: &Client, mut tweets: Vec<Tweet>) -> Vec<Tweet> {
+    let ids = tweets.iter().filter_map(|x| x.id()).collect::<Vec<i32>>();
+    if ids.is_empty() {
+        return tweets;
+    }
+
+    let rows = conn
+        .query("SELECT * FROM mentions WHERE tweet_id = ANY($1)", &[&ids])
+        .await
+        .unwrap();
+    let mut mentions: HashMap<i32, Vec<Mention>> = HashMap::new();
+    for r in rows {
+        mentions
+            .entry(r.get("tweet_id"))
+            .or_default()
+            .push(Mention {
+                account_id: r.get("account_id"),
+                handle: r.get("handle"),
+            });
+    }
+
+    let rows = conn
+        .query(
+            "SELECT * FROM media WHERE tweet_id = ANY($1) ORDER BY position",
+            &[&ids],
+        )
+        .await
+        .unwrap();
+    let mut media: HashMap<i32, Vec<Media>> = HashMap::new();
+    for r in rows {
+        media
+            .entry(r.get("tweet_id"))
+            .or_default()
+            .push(media_from_row(r));
+    }
+
+    let rows = conn
+        .query(
+            "SELECT DISTINCT ON (tweet_links.tweet_id) tweet_links.tweet_id, link_previews.*
+             FROM tweet_links JOIN link_previews ON link_previews.url = tweet_links.url
+             WHERE tweet_links.tweet_id = ANY($1) AND link_previews.title <> ''
+             ORDER BY tweet_links.tweet_id, tweet_links.position",
+            &[&ids],
+        )
+        .await
+        .unwrap();
+    let mut link_previews: HashMap<i32, LinkPreview> = HashMap::new();
+    for r in rows {
+        link_previews.insert(
+            r.get("tweet_id"),
+            LinkPreview {
+                url: r.get("url"),
+                title: r.get("title"),
+                description: r.get("description"),
+                image_url: r.get("image_url"),
+            },
+        );
+    }
+
+    for tweet in tweets.iter_mut() {
+        let id = tweet.id().unwrap();
+        tweet.link_preview = link_previews.remove(&id);
+        if let Some(x) = mentions.remove(&id) {
+            tweet.mentions = x;
         }
+        if let Some(x) = media.remove(&id) {
+            tweet.media = x;
+        }
+    }
+    tweets
+}
+
+fn media_from_row(r: Row) -> Media {
+    Media {
+        key: r.get("key"),
+        thumbnail_key: r.get("thumbnail_key"),
+        content_type: r.get("content_type"),
+        alt_text: r.get("alt_text"),
+        width: r.get("width"),
+        height: r.get("height"),
     }
 }
 
+/// Turns each clause into a SQL condition. Values only ever go into the parameters.
+fn compile_search(query: &SearchQuery) -> (Vec<String>, Vec<Box<dyn ToSql + Sync + Send>>) {
+    let mut conditions = Vec::new();
+    let mut params: Vec<Box<dyn ToSql + Sync + Send>> = Vec::new();
+    for clause in query.clauses.iter() {
+        let condition = match clause {
+            // The trigram index only helps with terms of three characters or more. Shorter ones,
+            // which are common in Japanese (e.g. 猫 or 東京), still match, but by scanning
+            // every tweet that the other conditions leave.
+            Clause::Contains(term) => {
+                params.push(Box::new(format!("%{}%", escape_like(term))));
+                format!("message ILIKE ${}", params.len())
+            }
+            Clause::Excludes(term) => {
+                params.push(Box::new(format!("%{}%", escape_like(term))));
+                format!("message NOT ILIKE ${}", params.len())
+            }
+            Clause::From(handle) => {
+                params.push(Box::new(handle.clone()));
+                format!(
+                    "posted_by IN (SELECT id FROM accounts WHERE LOWER(handle) = LOWER(${}))",
+                    params.len()
+                )
+            }
+            Clause::To(handle) => {
+                params.push(Box::new(handle.clone()));
+                format!(
+                    "id IN (SELECT mentions.tweet_id FROM mentions
+                     JOIN accounts ON accounts.id = mentions.account_id
+                     WHERE LOWER(accounts.handle) = LOWER(${}))",
+                    params.len()
+                )
+            }
+            Clause::Since(date) => {
+                params.push(Box::new(start_of_day(date)));
+                format!("posted_at >= ${}", params.len())
+            }
+            Clause::Until(date) => {
+                params.push(Box::new(start_of_day(date)));
+                format!("posted_at < ${}", params.len())
+            }
+            Clause::HasLinks => "message ~* 'https?://'".to_string(),
+        };
+        conditions.push(condition);
+    }
+    (conditions, params)
+}
+
+fn start_of_day(date: &NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+}
+
 impl From<Row> for Tweet {
     fn from(r: Row) -> Self {
-        Tweet::new(
+        let mut tweet = Tweet::new(
             r.get("id"),
             r.get("message"),
             r.get("posted_at"),
             r.get("posted_by"),
-        )
+        );
+        tweet.in_reply_to = r.get("in_reply_to");
+        tweet.edited_at = r.get("edited_at");
+        tweet.content_warning = r.get("content_warning");
+        tweet.deleted_at = r.get("deleted_at");
+        tweet.publish_at = r.get("publish_at");
+        tweet.pending = r.get("pending");
+        tweet
     }
 }
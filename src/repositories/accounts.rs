@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
 
 use crate::entities::Account;
@@ -7,5 +8,12 @@ use crate::entities::Account;
 pub trait Accounts {
     async fn find(&self, ids: HashSet<i32>) -> HashMap<i32, Account>;
     async fn find_by(&self, email: &str) -> Option<Account>;
+    async fn find_by_id(&self, id: i32) -> Option<Account>;
+    /// Looks the handle up case-insensitively.
+    async fn find_by_handle(&self, handle: &str) -> Option<Account>;
+    /// Accounts whose handle or display name starts with the prefix, ignoring case and
+    /// full-width/half-width differences. The prefix has to be normalized already.
+    async fn search(&self, prefix: &str, limit: i64) -> Vec<Account>;
+    async fn list_deletion_due(&self, now: DateTime<Utc>) -> Vec<Account>;
     async fn store(&self, entity: &Account);
 }
@@ -0,0 +1,12 @@
+use crate::entities::Draft;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait Drafts {
+    async fn find(&self, id: i32, account_id: i32) -> Option<Draft>;
+    /// The most recently updated first.
+    async fn list(&self, account_id: i32) -> Vec<Draft>;
+    /// Inserts or updates the draft, returning its id.
+    async fn store(&self, entity: &Draft) -> i32;
+    async fn delete(&self, id: i32, account_id: i32);
+}
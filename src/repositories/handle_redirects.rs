@@ -0,0 +1,9 @@
+use chrono::{DateTime, Utc};
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait HandleRedirects {
+    /// Returns the account a former handle still points to, if the redirect hasn't expired.
+    async fn find(&self, handle: &str, now: DateTime<Utc>) -> Option<i32>;
+    async fn store(&self, handle: &str, account_id: i32, expires_at: DateTime<Utc>);
+}
@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+
+use crate::entities::LinkPreview;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait LinkPreviews {
+    /// URLs posted in tweets whose page hasn't been fetched yet.
+    async fn list_pending(&self, limit: i64) -> Vec<String>;
+    /// Caches the preview and takes the URL off the pending list.
+    async fn store(&self, entity: &LinkPreview, fetched_at: DateTime<Utc>);
+}
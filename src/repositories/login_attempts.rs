@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+
+use crate::entities::LoginAttempt;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait LoginAttempts {
+    async fn find_by(&self, key: &str) -> Option<LoginAttempt>;
+    async fn record_failure(&self, key: &str, now: DateTime<Utc>);
+    async fn reset(&self, key: &str);
+}
@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+
+use crate::entities::LoginLink;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait LoginLinks {
+    async fn count_since(&self, account_id: i32, since: DateTime<Utc>) -> i64;
+    /// Marks the link as used and returns it, unless it is unknown, expired or already used.
+    async fn consume(&self, token_hash: &str, now: DateTime<Utc>) -> Option<LoginLink>;
+    async fn store(&self, entity: &LoginLink);
+}
@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+
+use crate::entities::PasswordReset;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait PasswordResets {
+    /// Marks the reset as used and returns it, unless it is unknown, expired or already used.
+    async fn consume(&self, token_hash: &str, now: DateTime<Utc>) -> Option<PasswordReset>;
+    async fn store(&self, entity: &PasswordReset);
+}
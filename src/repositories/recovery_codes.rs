@@ -0,0 +1,8 @@
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait RecoveryCodes {
+    /// Discards the account's existing codes and stores the new ones.
+    async fn replace(&self, account_id: i32, code_hashes: Vec<String>);
+    /// Marks the code as used, returning whether it was a valid, unused code.
+    async fn consume(&self, account_id: i32, code_hash: &str) -> bool;
+}
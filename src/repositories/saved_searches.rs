@@ -0,0 +1,10 @@
+use crate::entities::SavedSearch;
+
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait SavedSearches {
+    async fn list(&self, account_id: i32) -> Vec<SavedSearch>;
+    /// Saving the same query twice keeps a single entry.
+    async fn store(&self, entity: &SavedSearch);
+    async fn delete(&self, id: i32, account_id: i32);
+}
@@ -0,0 +1,5 @@
+#[cfg_attr(test, mockall::automock)]
+#[axum::async_trait]
+pub trait Sessions {
+    async fn delete_all(&self, account_id: i32);
+}
@@ -1,9 +1,50 @@
-use crate::entities::Tweet;
+use chrono::{DateTime, Utc};
+
+use crate::entities::{Media, SearchQuery, Tweet, TweetRevision};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchOrder {
+    Relevance,
+    Recency,
+}
 
 #[cfg_attr(test, mockall::automock)]
 #[axum::async_trait]
 pub trait Tweets {
     async fn find(&self, id: i32) -> Option<Tweet>;
     async fn list(&self) -> Vec<Tweet>;
-    async fn store(&self, entity: &Tweet);
+    async fn list_by(&self, account_id: i32) -> Vec<Tweet>;
+    async fn list_mentioning(&self, account_id: i32) -> Vec<Tweet>;
+    /// `tag` has to be normalized already.
+    async fn list_tagged(&self, tag: &str) -> Vec<Tweet>;
+    /// Finds tweets matching every clause of the query. Text matching ignores case.
+    async fn search(&self, query: SearchQuery, order: SearchOrder) -> Vec<Tweet>;
+    /// The whole thread the tweet is part of, from its first tweet on.
+    async fn list_thread(&self, id: i32) -> Vec<Tweet>;
+    /// The earlier versions of the tweet, newest first.
+    async fn list_revisions(&self, id: i32) -> Vec<TweetRevision>;
+    /// Scheduled tweets are left out of everything above until they are published.
+    async fn find_scheduled(&self, id: i32) -> Option<Tweet>;
+    /// The author's scheduled tweets, the next to be published first. Tweets pending for the
+    /// undo delay are left out.
+    async fn list_scheduled_by(&self, account_id: i32) -> Vec<Tweet>;
+    /// Moves the author's tweet to the trash if it is still unpublished, in one statement so it
+    /// can't race the publisher. Returns whether it was.
+    async fn cancel_scheduled(&self, id: i32, account_id: i32, now: DateTime<Utc>) -> bool;
+    /// Publishes the scheduled tweets that are due, each exactly once even when several
+    /// instances call it at the same time. Returns how many were published.
+    async fn publish_due(&self, now: DateTime<Utc>) -> u64;
+    /// Deleted tweets are left out of everything above; these find them for the trash.
+    async fn find_deleted(&self, id: i32) -> Option<Tweet>;
+    /// The author's tweets deleted after `since`, most recently deleted first.
+    async fn list_deleted_by(&self, account_id: i32, since: DateTime<Utc>) -> Vec<Tweet>;
+    /// Removes the tweets deleted before `before` for good, returning their media to clean up.
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Vec<Media>;
+    /// Inserts a new tweet, or updates an existing one, including deleting and restoring it.
+    /// Updating a changed message keeps the previous version as a revision. Returns the id.
+    async fn store(&self, entity: &Tweet) -> i32;
+    /// Inserts new tweets as a thread, each replying to the one before it, all or none of them.
+    async fn store_thread(&self, entities: &[Tweet]);
+    /// Removes all the account's tweets for good, returning their media to clean up.
+    async fn delete_all_by(&self, account_id: i32) -> Vec<Media>;
 }
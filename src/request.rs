@@ -1,5 +1,8 @@
-use crate::constants::{database_url, AXUM_SESSION_COOKIE_NAME, AXUM_SESSION_USER_ID_KEY};
-use async_session::SessionStore;
+use crate::constants::{
+    database_url, AXUM_SESSION_COOKIE_NAME, AXUM_SESSION_PENDING_USER_ID_KEY,
+    AXUM_SESSION_USER_ID_KEY,
+};
+use async_session::{Session, SessionStore};
 use async_sqlx_session::PostgresSessionStore;
 use axum::extract::{FromRequest, RequestParts, TypedHeader};
 use axum::headers::Cookie;
@@ -22,23 +25,53 @@ where
     async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
         let redirect = || Redirect::to(Uri::from_static("/login"));
 
-        let database_url = database_url();
-        let store = PostgresSessionStore::new(&database_url)
-            .await
-            .map_err(|_| redirect())?;
-        let cookies = Option::<TypedHeader<Cookie>>::from_request(req)
-            .await
-            .unwrap()
-            .ok_or(redirect())?;
-        let session_str = cookies.get(AXUM_SESSION_COOKIE_NAME).ok_or(redirect())?;
-        let session = store
-            .load_session(session_str.to_string())
-            .await
-            .map_err(|_| redirect())?;
-        let session = session.ok_or(redirect())?;
+        let (_, session) = load_session(req).await.ok_or_else(redirect)?;
         let context = UserContext {
-            user_id: session.get::<i32>(AXUM_SESSION_USER_ID_KEY).unwrap(),
+            user_id: session
+                .get::<i32>(AXUM_SESSION_USER_ID_KEY)
+                .ok_or_else(redirect)?,
         };
         Ok(context)
     }
 }
+
+/// A session that has passed the password check but still awaits the second factor.
+pub struct PendingUserContext {
+    pub user_id: i32,
+    pub session_token: String,
+}
+
+#[axum::async_trait]
+impl<B> FromRequest<B> for PendingUserContext
+where
+    B: Send,
+{
+    type Rejection = Redirect;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let redirect = || Redirect::to(Uri::from_static("/login"));
+
+        let (session_token, session) = load_session(req).await.ok_or_else(redirect)?;
+        let context = PendingUserContext {
+            user_id: session
+                .get::<i32>(AXUM_SESSION_PENDING_USER_ID_KEY)
+                .ok_or_else(redirect)?,
+            session_token,
+        };
+        Ok(context)
+    }
+}
+
+async fn load_session<B>(req: &mut RequestParts<B>) -> Option<(String, Session)>
+where
+    B: Send,
+{
+    let database_url = database_url();
+    let store = PostgresSessionStore::new(&database_url).await.ok()?;
+    let cookies = Option::<TypedHeader<Cookie>>::from_request(req)
+        .await
+        .unwrap()?;
+    let session_str = cookies.get(AXUM_SESSION_COOKIE_NAME)?.to_string();
+    let session = store.load_session(session_str.clone()).await.ok()??;
+    Some((session_str, session))
+}
@@ -0,0 +1,243 @@
+use chrono::{Duration, Utc};
+
+use crate::entities::Account;
+use crate::media_store::MediaStore;
+use crate::repositories::{Accounts, Sessions, Tweets};
+use crate::request::UserContext;
+use crate::services::SettingsError;
+
+#[derive(Debug, PartialEq)]
+pub enum AccountDeletion {
+    Scheduled,
+    Deleted,
+}
+
+/// Deletes the account right away, or after `grace_days` if that is more than 0.
+/// Either way the user is signed out everywhere.
+pub async fn delete_account(
+    repo: &impl Accounts,
+    tweet_repo: &impl Tweets,
+    session_repo: &impl Sessions,
+    media_store: &impl MediaStore,
+    user_context: &UserContext,
+    grace_days: i64,
+    password: &str,
+) -> Result<AccountDeletion, SettingsError> {
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    if !account.matches_password(password) {
+        return Err(SettingsError::InvalidPassword);
+    }
+
+    if grace_days > 0 {
+        account.schedule_deletion(Utc::now() + Duration::days(grace_days));
+        repo.store(&account).await;
+        session_repo.delete_all(user_context.user_id).await;
+        Ok(AccountDeletion::Scheduled)
+    } else {
+        erase_account(repo, tweet_repo, session_repo, media_store, &mut account).await;
+        Ok(AccountDeletion::Deleted)
+    }
+}
+
+pub async fn purge_deleted_accounts(
+    repo: &impl Accounts,
+    tweet_repo: &impl Tweets,
+    session_repo: &impl Sessions,
+    media_store: &impl MediaStore,
+) {
+    for mut account in repo.list_deletion_due(Utc::now()).await {
+        erase_account(repo, tweet_repo, session_repo, media_store, &mut account).await;
+    }
+}
+
+async fn erase_account(
+    repo: &impl Accounts,
+    tweet_repo: &impl Tweets,
+    session_repo: &impl Sessions,
+    media_store: &impl MediaStore,
+    account: &mut Account,
+) {
+    let account_id = account.id().unwrap();
+    for media in tweet_repo.delete_all_by(account_id).await {
+        media_store.delete(&media.key).await;
+        media_store.delete(&media.thumbnail_key).await;
+    }
+    session_repo.delete_all(account_id).await;
+    account.erase(Utc::now());
+    repo.store(account).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use sha2::{Digest, Sha256};
+
+    use super::AccountDeletion;
+    use crate::entities::{Account, Media};
+    use crate::media_store::MockMediaStore;
+    use crate::repositories::{MockAccounts, MockSessions, MockTweets};
+    use crate::request::UserContext;
+    use crate::services::SettingsError;
+
+    fn account(id: i32) -> Account {
+        Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("{:x}", Sha256::digest(format!("password{}", id).as_bytes())),
+            format!("display_name{}", id),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_delete_account() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| {
+                e.id() == Some(1)
+                    && e.is_deleted()
+                    && e.email == "deleted-1@invalid"
+                    && e.display_name.is_empty()
+                    && !e.matches_password("password1")
+            })
+            .once()
+            .return_const(());
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_delete_all_by()
+            .withf(|id| *id == 1)
+            .once()
+            .returning(|_| {
+                vec![Media {
+                    key: "a.png".to_string(),
+                    thumbnail_key: "a_thumb.png".to_string(),
+                    content_type: "image/png".to_string(),
+                    alt_text: String::new(),
+                    width: 1,
+                    height: 1,
+                }]
+            });
+
+        let mut sessions = MockSessions::new();
+        sessions
+            .expect_delete_all()
+            .withf(|id| *id == 1)
+            .once()
+            .return_const(());
+
+        let mut media_store = MockMediaStore::new();
+        media_store
+            .expect_delete()
+            .withf(|key| key == "a.png" || key == "a_thumb.png")
+            .times(2)
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::delete_account(
+            &accounts,
+            &tweets,
+            &sessions,
+            &media_store,
+            &user_context,
+            0,
+            "password1",
+        )
+        .await;
+        assert_eq!(result, Ok(AccountDeletion::Deleted));
+    }
+
+    #[tokio::test]
+    async fn test_delete_account_scheduled() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| e.deletion_scheduled_at.is_some() && !e.is_deleted())
+            .once()
+            .return_const(());
+
+        let mut tweets = MockTweets::new();
+        tweets.expect_delete_all_by().never();
+
+        let mut sessions = MockSessions::new();
+        sessions
+            .expect_delete_all()
+            .withf(|id| *id == 1)
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::delete_account(
+            &accounts,
+            &tweets,
+            &sessions,
+            &MockMediaStore::new(),
+            &user_context,
+            7,
+            "password1",
+        )
+        .await;
+        assert_eq!(result, Ok(AccountDeletion::Scheduled));
+    }
+
+    #[tokio::test]
+    async fn test_delete_account_wrong_password() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_store().never();
+
+        let mut tweets = MockTweets::new();
+        tweets.expect_delete_all_by().never();
+
+        let mut sessions = MockSessions::new();
+        sessions.expect_delete_all().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::delete_account(
+            &accounts,
+            &tweets,
+            &sessions,
+            &MockMediaStore::new(),
+            &user_context,
+            0,
+            "password2",
+        )
+        .await;
+        assert_eq!(result, Err(SettingsError::InvalidPassword));
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted_accounts() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_list_deletion_due().returning(|_| {
+            let mut account = account(2);
+            account.schedule_deletion(Utc::now() - Duration::days(1));
+            vec![account]
+        });
+        accounts
+            .expect_store()
+            .withf(|e| e.id() == Some(2) && e.is_deleted() && e.deletion_scheduled_at.is_none())
+            .once()
+            .return_const(());
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_delete_all_by()
+            .withf(|id| *id == 2)
+            .once()
+            .returning(|_| vec![]);
+
+        let mut sessions = MockSessions::new();
+        sessions.expect_delete_all().once().return_const(());
+
+        super::purge_deleted_accounts(&accounts, &tweets, &sessions, &MockMediaStore::new()).await;
+    }
+}
@@ -1,41 +1,232 @@
 use async_session::{Session, SessionStore};
 use async_sqlx_session::PostgresSessionStore;
+use chrono::Utc;
+use std::net::IpAddr;
 use std::time::Duration;
 
-use crate::constants::{database_url, AXUM_SESSION_COOKIE_NAME, AXUM_SESSION_USER_ID_KEY};
-use crate::entities::Account;
-use crate::repositories::Accounts;
+use crate::constants::{
+    base_url, database_url, AXUM_SESSION_COOKIE_NAME, AXUM_SESSION_PENDING_USER_ID_KEY,
+    AXUM_SESSION_USER_ID_KEY,
+};
+use crate::entities::{Account, LoginAttempt};
+use crate::mailer::{Email, Mailer};
+use crate::repositories::{Accounts, HandleRedirects, LoginAttempts};
+use crate::request::UserContext;
+use crate::token;
 
-pub async fn create_account(repo: &impl Accounts, email: &str, password: &str, display_name: &str) {
-    let new_account = Account::create(email, password, display_name);
+const EMAIL_VERIFICATION_TTL_SECONDS: i64 = 86400;
+
+#[derive(Debug, PartialEq)]
+pub enum AccountError {
+    InvalidEmail,
+    InvalidHandle,
+    HandleTaken,
+}
+
+pub async fn create_account(
+    repo: &impl Accounts,
+    redirect_repo: &impl HandleRedirects,
+    mailer: &impl Mailer,
+    email: &str,
+    password: &str,
+    display_name: &str,
+    handle: &str,
+) -> Result<(), AccountError> {
+    if !Account::is_valid_email(email) {
+        return Err(AccountError::InvalidEmail);
+    }
+    let handle = handle.trim().trim_start_matches('@');
+    if !Account::is_valid_handle(handle) {
+        return Err(AccountError::InvalidHandle);
+    }
+    if !handle_available(repo, redirect_repo, handle, None).await {
+        return Err(AccountError::HandleTaken);
+    }
+
+    let new_account = Account::create(email, password, display_name, handle);
     repo.store(&new_account).await;
+    if let Some(account) = repo.find_by(email).await {
+        if !account.email_verified {
+            send_verification_email(mailer, &account).await;
+        }
+    }
+    Ok(())
+}
+
+/// A handle is taken by another account, or reserved while it still redirects to one.
+pub(super) async fn handle_available(
+    repo: &impl Accounts,
+    redirect_repo: &impl HandleRedirects,
+    handle: &str,
+    account_id: Option<i32>,
+) -> bool {
+    if let Some(account) = repo.find_by_handle(handle).await {
+        if account.id() != account_id {
+            return false;
+        }
+    }
+    match redirect_repo.find(handle, Utc::now()).await {
+        Some(redirect_to) => Some(redirect_to) == account_id,
+        None => true,
+    }
+}
+
+pub async fn resend_verification_email(
+    repo: &impl Accounts,
+    mailer: &impl Mailer,
+    user_context: &UserContext,
+) {
+    if let Some(account) = repo.find_by_id(user_context.user_id).await {
+        if !account.email_verified {
+            send_verification_email(mailer, &account).await;
+        }
+    }
+}
+
+pub async fn verify_email(repo: &impl Accounts, token: &str) -> bool {
+    let mut parts = token.splitn(3, '.');
+    let id = parts.next().and_then(|x| x.parse::<i32>().ok());
+    let expires_at = parts.next().and_then(|x| x.parse::<i64>().ok());
+    let (id, expires_at, signature) = match (id, expires_at, parts.next()) {
+        (Some(id), Some(expires_at), Some(signature)) => (id, expires_at, signature),
+        _ => return false,
+    };
+    if Utc::now().timestamp() > expires_at {
+        return false;
+    }
+
+    let mut account = match repo.find_by_id(id).await {
+        Some(account) => account,
+        None => return false,
+    };
+    if !token::verify(&verification_payload(&account, expires_at), signature) {
+        return false;
+    }
+    if !account.email_verified {
+        account.verify_email();
+        repo.store(&account).await;
+    }
+    true
+}
+
+pub(super) async fn send_verification_email(mailer: &impl Mailer, account: &Account) {
+    let expires_at =
+        (Utc::now() + chrono::Duration::seconds(EMAIL_VERIFICATION_TTL_SECONDS)).timestamp();
+    let signature = token::sign(&verification_payload(account, expires_at));
+    let url = format!(
+        "{}/accounts/verify?token={}.{}.{}",
+        base_url(),
+        account.id().unwrap(),
+        expires_at,
+        signature
+    );
+    let email = Email {
+        to: account.email.clone(),
+        subject: "メールアドレスの確認".to_string(),
+        body: format!(
+            "以下のリンクを開いてメールアドレスを確認してください。\n\n{}\n\nこのリンクの有効期限は24時間です。",
+            url
+        ),
+    };
+    mailer.send(&email).await;
+}
+
+/// The email is part of the signed payload so that a token stops working once the address changes.
+fn verification_payload(account: &Account, expires_at: i64) -> String {
+    format!(
+        "email-verification:{}:{}:{}",
+        account.id().unwrap(),
+        account.email,
+        expires_at
+    )
 }
 
 pub async fn create_session(
     repo: &impl Accounts,
+    attempt_repo: &impl LoginAttempts,
     email: &str,
     password: &str,
-) -> Option<SessionToken> {
-    let account = repo.find_by(email).await;
-    if let Some(account) = account {
-        if !account.matches_password(password) {
-            return None;
+    client_ip: IpAddr,
+) -> Result<SessionToken, SessionError> {
+    let now = Utc::now();
+    let attempt_keys = [
+        LoginAttempt::email_key(email),
+        LoginAttempt::ip_key(client_ip),
+    ];
+    for key in attempt_keys.iter() {
+        if let Some(attempt) = attempt_repo.find_by(key).await {
+            if attempt.is_blocked(now) {
+                return Err(SessionError::TooManyAttempts);
+            }
         }
+    }
 
-        let database_url = database_url();
-        let store = PostgresSessionStore::new(&database_url).await.unwrap();
+    let account = repo.find_by(email).await;
+    let authenticated = match &account {
+        Some(account) => account.matches_password(password),
+        None => Account::simulate_password_check(password),
+    };
+    let mut account = match account {
+        Some(account) if authenticated => account,
+        _ => {
+            for key in attempt_keys.iter() {
+                attempt_repo.record_failure(key, now).await;
+            }
+            return Err(SessionError::Invalid);
+        }
+    };
+    attempt_repo.reset(&attempt_keys[0]).await;
 
-        let mut session = Session::new();
-        session
-            .insert(AXUM_SESSION_USER_ID_KEY, account.id().unwrap())
-            .unwrap();
-        session.expire_in(Duration::from_secs(604800));
+    Ok(issue_session(repo, &mut account).await)
+}
 
-        let cookie = store.store_session(session).await.unwrap().unwrap();
+/// Signing back in during the grace period keeps the account.
+pub(super) async fn cancel_scheduled_deletion(repo: &impl Accounts, account: &mut Account) {
+    if account.deletion_scheduled_at.is_some() {
+        account.cancel_deletion();
+        repo.store(account).await;
+    }
+}
 
-        Some(SessionToken::new(&cookie))
+/// Accounts with two-factor authentication only get a short-lived pending session
+/// until the second step succeeds, and a scheduled deletion is only cancelled by a full one.
+pub(super) async fn issue_session(repo: &impl Accounts, account: &mut Account) -> SessionToken {
+    let account_id = account.id().unwrap();
+    if account.two_factor_enabled() {
+        let cookie = store_session(
+            AXUM_SESSION_PENDING_USER_ID_KEY,
+            account_id,
+            PENDING_SESSION_MAX_AGE,
+        )
+        .await;
+        SessionToken::pending(&cookie)
     } else {
-        None
+        cancel_scheduled_deletion(repo, account).await;
+        issue_verified_session(account_id).await
+    }
+}
+
+pub(super) async fn issue_verified_session(account_id: i32) -> SessionToken {
+    let cookie = store_session(AXUM_SESSION_USER_ID_KEY, account_id, SESSION_MAX_AGE).await;
+    SessionToken::new(&cookie)
+}
+
+async fn store_session(key: &str, account_id: i32, max_age: usize) -> String {
+    let database_url = database_url();
+    let store = PostgresSessionStore::new(&database_url).await.unwrap();
+
+    let mut session = Session::new();
+    session.insert(key, account_id).unwrap();
+    session.expire_in(Duration::from_secs(max_age as u64));
+
+    store.store_session(session).await.unwrap().unwrap()
+}
+
+pub(super) async fn destroy_session(token: &str) {
+    let database_url = database_url();
+    let store = PostgresSessionStore::new(&database_url).await.unwrap();
+    if let Ok(Some(session)) = store.load_session(token.to_string()).await {
+        store.destroy_session(session).await.ok();
     }
 }
 
@@ -43,16 +234,35 @@ pub fn clear_session() -> SessionToken {
     SessionToken::clear()
 }
 
+#[derive(Debug, PartialEq)]
+pub enum SessionError {
+    Invalid,
+    TooManyAttempts,
+}
+
+const SESSION_MAX_AGE: usize = 604800;
+const PENDING_SESSION_MAX_AGE: usize = 300;
+
 pub struct SessionToken {
     token: String,
     max_age: usize,
+    pending: bool,
 }
 
 impl SessionToken {
     pub fn new(token: &str) -> SessionToken {
         SessionToken {
             token: token.to_string(),
-            max_age: 604800,
+            max_age: SESSION_MAX_AGE,
+            pending: false,
+        }
+    }
+
+    pub fn pending(token: &str) -> SessionToken {
+        SessionToken {
+            token: token.to_string(),
+            max_age: PENDING_SESSION_MAX_AGE,
+            pending: true,
         }
     }
 
@@ -60,8 +270,13 @@ impl SessionToken {
         SessionToken {
             token: "deleted".to_string(),
             max_age: 0,
+            pending: false,
         }
     }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
 }
 
 impl SessionToken {
@@ -75,18 +290,30 @@ impl SessionToken {
 
 #[cfg(test)]
 mod tests {
+    use chrono::Utc;
     use sha2::{Digest, Sha256};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::{Arc, Mutex};
 
-    use crate::entities::Account;
-    use crate::repositories::MockAccounts;
+    use super::{AccountError, SessionError};
+    use crate::entities::{Account, LoginAttempt};
+    use crate::mailer::MockMailer;
+    use crate::repositories::{MockAccounts, MockHandleRedirects, MockLoginAttempts};
+    use crate::request::UserContext;
 
     fn account(id: i32) -> Account {
-        Account::new(
+        let mut account = Account::new(
             id,
             format!("{}@example.com", id),
             to_sha256(format!("password{}", id)),
             format!("display_name{}", id),
-        )
+        );
+        account.handle = format!("handle{}", id);
+        account
+    }
+
+    fn ip() -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))
     }
 
     fn to_sha256(str: String) -> String {
@@ -105,18 +332,137 @@ mod tests {
                 e.email == account.email
                     && e.hashed_password == account.hashed_password
                     && e.display_name == account.display_name
+                    && e.handle == account.handle
+                    && !e.email_verified
             })
             .once()
             .return_const(());
+        accounts.expect_find_by().returning(|_| Some(account(1)));
+        accounts.expect_find_by_handle().returning(|_| None);
+
+        let mut redirects = MockHandleRedirects::new();
+        redirects.expect_find().returning(|_, _| None);
+
+        let mut mailer = MockMailer::new();
+        mailer
+            .expect_send()
+            .withf(|e| e.to == "1@example.com" && e.body.contains("/accounts/verify?token=1."))
+            .once()
+            .return_const(());
 
         let account = account(1);
-        super::create_account(
+        let result = super::create_account(
             &accounts,
+            &redirects,
+            &mailer,
             &account.email,
             "password1",
             &account.display_name,
+            "@handle1",
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_create_account_handle_taken() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_handle()
+            .returning(|_| Some(account(2)));
+        accounts.expect_store().never();
+
+        let mut redirects = MockHandleRedirects::new();
+        redirects.expect_find().returning(|_, _| None);
+
+        let result = super::create_account(
+            &accounts,
+            &redirects,
+            &MockMailer::new(),
+            "1@example.com",
+            "password1",
+            "display_name1",
+            "HANDLE2",
+        )
+        .await;
+        assert_eq!(result, Err(AccountError::HandleTaken));
+    }
+
+    #[tokio::test]
+    async fn test_create_account_invalid_email() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_store().never();
+
+        for email in [
+            "",
+            "example.com",
+            "@example.com",
+            "a@example",
+            "a b@example.com",
+        ] {
+            let result = super::create_account(
+                &accounts,
+                &MockHandleRedirects::new(),
+                &MockMailer::new(),
+                email,
+                "password1",
+                "display_name1",
+                "handle1",
+            )
+            .await;
+            assert_eq!(result, Err(AccountError::InvalidEmail), "{}", email);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_account_invalid_handle() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_store().never();
+
+        let result = super::create_account(
+            &accounts,
+            &MockHandleRedirects::new(),
+            &MockMailer::new(),
+            "1@example.com",
+            "password1",
+            "display_name1",
+            "no spaces",
         )
         .await;
+        assert_eq!(result, Err(AccountError::InvalidHandle));
+    }
+
+    #[tokio::test]
+    async fn test_verify_email() {
+        let mut mailer = MockMailer::new();
+        let token = Arc::new(Mutex::new(String::new()));
+        let sent_token = token.clone();
+        mailer.expect_send().returning(move |e| {
+            let url = e.body.lines().find(|x| x.contains("token=")).unwrap();
+            *sent_token.lock().unwrap() = url.split("token=").nth(1).unwrap().to_string();
+        });
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|_| Some(account(1)));
+        super::resend_verification_email(&accounts, &mailer, &UserContext { user_id: 1 }).await;
+
+        accounts
+            .expect_store()
+            .withf(|e| e.id() == Some(1) && e.email_verified)
+            .once()
+            .return_const(());
+        let token = token.lock().unwrap().clone();
+        assert!(super::verify_email(&accounts, &token).await);
+    }
+
+    #[tokio::test]
+    async fn test_verify_email_tampered() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|_| Some(account(1)));
+        accounts.expect_store().never();
+
+        let expires_at = Utc::now().timestamp() + 60;
+        let token = format!("1.{}.{}", expires_at, "00".repeat(32));
+        assert!(!super::verify_email(&accounts, &token).await);
     }
 
     #[tokio::test]
@@ -124,9 +470,41 @@ mod tests {
         let mut accounts = MockAccounts::new();
         accounts.expect_find_by().returning(|_| Some(account(1)));
 
+        let mut attempts = MockLoginAttempts::new();
+        attempts.expect_find_by().returning(|_| None);
+        attempts.expect_record_failure().never();
+        attempts
+            .expect_reset()
+            .withf(|key| key == "email:1@example.com")
+            .once()
+            .return_const(());
+
+        let account = account(1);
+        let result =
+            super::create_session(&accounts, &attempts, &account.email, "password1", ip()).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_create_session_two_factor_keeps_deletion() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by().returning(|_| {
+            let mut account = account(1);
+            account.totp_secret = Some("JBSWY3DPEHPK3PXP".to_string());
+            account.schedule_deletion(Utc::now() + chrono::Duration::days(7));
+            Some(account)
+        });
+        // The password alone doesn't prove it is the owner, so the deletion stays scheduled.
+        accounts.expect_store().never();
+
+        let mut attempts = MockLoginAttempts::new();
+        attempts.expect_find_by().returning(|_| None);
+        attempts.expect_reset().return_const(());
+
         let account = account(1);
-        let result = super::create_session(&accounts, &account.email, "password1").await;
-        assert_eq!(result.is_some(), true);
+        let result =
+            super::create_session(&accounts, &attempts, &account.email, "password1", ip()).await;
+        assert!(result.is_ok());
     }
 
     #[tokio::test]
@@ -134,8 +512,50 @@ mod tests {
         let mut accounts = MockAccounts::new();
         accounts.expect_find_by().returning(|_| None);
 
+        let mut attempts = MockLoginAttempts::new();
+        attempts.expect_find_by().returning(|_| None);
+        attempts.expect_record_failure().times(2).return_const(());
+
+        let account = account(1);
+        let result =
+            super::create_session(&accounts, &attempts, &account.email, "password1", ip()).await;
+        assert_eq!(result.err(), Some(SessionError::Invalid));
+    }
+
+    #[tokio::test]
+    async fn test_create_session_wrong_password() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by().returning(|_| Some(account(1)));
+
+        let mut attempts = MockLoginAttempts::new();
+        attempts.expect_find_by().returning(|_| None);
+        attempts
+            .expect_record_failure()
+            .withf(|key, _| key == "email:1@example.com" || key == "ip:192.0.2.1")
+            .times(2)
+            .return_const(());
+        attempts.expect_reset().never();
+
+        let account = account(1);
+        let result =
+            super::create_session(&accounts, &attempts, &account.email, "password2", ip()).await;
+        assert_eq!(result.err(), Some(SessionError::Invalid));
+    }
+
+    #[tokio::test]
+    async fn test_create_session_too_many_attempts() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by().never();
+
+        let mut attempts = MockLoginAttempts::new();
+        attempts
+            .expect_find_by()
+            .returning(|key| Some(LoginAttempt::new(key.to_string(), 10, Utc::now())));
+        attempts.expect_record_failure().never();
+
         let account = account(1);
-        let result = super::create_session(&accounts, &account.email, "password1").await;
-        assert_eq!(result.is_some(), false);
+        let result =
+            super::create_session(&accounts, &attempts, &account.email, "password1", ip()).await;
+        assert_eq!(result.err(), Some(SessionError::TooManyAttempts));
     }
 }
@@ -0,0 +1,289 @@
+use chrono::Utc;
+
+use crate::entities::Draft;
+use crate::media_store::MediaStore;
+use crate::repositories::{Accounts, Drafts, Tweets};
+use crate::request::UserContext;
+use crate::services::{create_tweet, NewTweet, Posted, TweetError};
+use crate::views::{DraftItem, DraftList};
+
+const MAX_DRAFTS: usize = 50;
+/// Drafts may run over the tweet limit while being written, but not without bound.
+const MAX_DRAFT_LENGTH: usize = 10_000;
+
+#[derive(Debug, PartialEq)]
+pub enum DraftError {
+    /// Empty, or longer than a draft may be.
+    Invalid,
+    TooMany,
+    /// The draft being saved to has been posted or discarded, e.g. in another tab.
+    NotFound,
+}
+
+pub async fn list_drafts(repo: &impl Drafts, user_context: &UserContext) -> DraftList {
+    DraftList {
+        drafts: repo
+            .list(user_context.user_id)
+            .await
+            .into_iter()
+            .map(|x| x.into())
+            .collect(),
+        error: String::new(),
+    }
+}
+
+/// For resuming the draft in the composer.
+pub async fn find_draft(
+    repo: &impl Drafts,
+    user_context: &UserContext,
+    id: i32,
+) -> Option<DraftItem> {
+    repo.find(id, user_context.user_id).await.map(|x| x.into())
+}
+
+/// Saves to the draft `id` if it is given, or to a new one otherwise. Returns the id of the draft.
+/// An empty `content_warning` is none.
+pub async fn save_draft(
+    repo: &impl Drafts,
+    user_context: &UserContext,
+    id: Option<i32>,
+    message: &str,
+    content_warning: &str,
+) -> Result<i32, DraftError> {
+    let content_warning = Some(content_warning.trim()).filter(|x| !x.is_empty());
+    if message.trim().is_empty()
+        || message.chars().count() > MAX_DRAFT_LENGTH
+        || content_warning.map(|x| x.chars().count()).unwrap_or(0) > MAX_DRAFT_LENGTH
+    {
+        return Err(DraftError::Invalid);
+    }
+    if let Some(id) = id {
+        let mut draft = repo
+            .find(id, user_context.user_id)
+            .await
+            .ok_or(DraftError::NotFound)?;
+        draft.update(message, content_warning, Utc::now());
+        return Ok(repo.store(&draft).await);
+    }
+    if repo.list(user_context.user_id).await.len() >= MAX_DRAFTS {
+        return Err(DraftError::TooMany);
+    }
+    let draft = Draft::create(user_context.user_id, message, content_warning);
+    Ok(repo.store(&draft).await)
+}
+
+/// Posts the draft with the same checks as the composer, and removes it once posted.
+pub async fn publish_draft(
+    repo: &impl Drafts,
+    tweet_repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    media_store: &impl MediaStore,
+    user_context: &UserContext,
+    id: i32,
+) -> Result<Option<Posted>, TweetError> {
+    let draft = match repo.find(id, user_context.user_id).await {
+        Some(draft) => draft,
+        None => return Ok(None),
+    };
+    let posted = create_tweet(
+        tweet_repo,
+        account_repo,
+        media_store,
+        user_context,
+        NewTweet {
+            message: draft.message,
+            content_warning: draft.content_warning.unwrap_or_default(),
+            ..Default::default()
+        },
+    )
+    .await?;
+    repo.delete(id, user_context.user_id).await;
+    Ok(Some(posted))
+}
+
+pub async fn discard_draft(repo: &impl Drafts, user_context: &UserContext, id: i32) {
+    repo.delete(id, user_context.user_id).await;
+}
+
+impl From<Draft> for DraftItem {
+    fn from(e: Draft) -> Self {
+        DraftItem {
+            id: e.id().unwrap_or(-1),
+            updated_at: e.updated_at.format("%Y/%m/%d %H:%M").to_string(),
+            message: e.message,
+            content_warning: e.content_warning.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::entities::{Account, Draft};
+    use crate::media_store::MockMediaStore;
+    use crate::repositories::{MockAccounts, MockDrafts, MockTweets};
+    use crate::request::UserContext;
+    use crate::services::{DraftError, Posted, TweetError};
+
+    fn draft(id: i32, account_id: i32, message: &str) -> Draft {
+        Draft::new(
+            id,
+            account_id,
+            message.to_string(),
+            Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+        )
+    }
+
+    fn account(id: i32) -> Account {
+        let mut account = Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("password{}", id),
+            format!("display_name{}", id),
+        );
+        account.email_verified = true;
+        account
+    }
+
+    #[tokio::test]
+    async fn test_save_draft() {
+        let mut drafts = MockDrafts::new();
+        drafts
+            .expect_find()
+            .returning(|id, account_id| (id == 1).then(|| draft(id, account_id, "before")));
+        drafts
+            .expect_store()
+            .withf(|e| e.id() == Some(1) && e.message == "after" && e.content_warning.is_none())
+            .once()
+            .returning(|e| e.id().unwrap());
+        drafts
+            .expect_list()
+            .returning(|account_id| vec![draft(1, account_id, "before")]);
+        drafts
+            .expect_store()
+            .withf(|e| {
+                e.id().is_none()
+                    && e.message == "new"
+                    && e.content_warning.as_deref() == Some("ネタバレ")
+            })
+            .once()
+            .return_const(2);
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::save_draft(&drafts, &user_context, Some(1), "after", " ").await;
+        assert_eq!(result, Ok(1));
+        let result = super::save_draft(&drafts, &user_context, None, "new", "ネタバレ").await;
+        assert_eq!(result, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_save_draft_discarded() {
+        let mut drafts = MockDrafts::new();
+        drafts.expect_find().returning(|_, _| None);
+        drafts.expect_list().returning(|_| Vec::new());
+        drafts.expect_store().never();
+
+        // The draft was discarded in another tab, so saving to it doesn't bring it back.
+        let user_context = UserContext { user_id: 1 };
+        let result = super::save_draft(&drafts, &user_context, Some(3), "after", "").await;
+        assert_eq!(result, Err(DraftError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_save_draft_rejected() {
+        let mut drafts = MockDrafts::new();
+        drafts.expect_find().returning(|_, _| None);
+        drafts
+            .expect_list()
+            .returning(|account_id| (0..50).map(|x| draft(x, account_id, "draft")).collect());
+        drafts.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        assert_eq!(
+            super::save_draft(&drafts, &user_context, None, " \n", "").await,
+            Err(DraftError::Invalid)
+        );
+        let long = "あ".repeat(10_001);
+        assert_eq!(
+            super::save_draft(&drafts, &user_context, None, &long, "").await,
+            Err(DraftError::Invalid)
+        );
+        assert_eq!(
+            super::save_draft(&drafts, &user_context, None, "draft", "").await,
+            Err(DraftError::TooMany)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publish_draft() {
+        let mut drafts = MockDrafts::new();
+        drafts.expect_find().returning(|id, account_id| {
+            let mut draft = draft(id, account_id, "#rust");
+            draft.content_warning = Some("ネタバレ".to_string());
+            Some(draft)
+        });
+        drafts
+            .expect_delete()
+            .withf(|id, account_id| *id == 1 && *account_id == 1)
+            .once()
+            .return_const(());
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store()
+            .withf(|e| {
+                e.message == "#rust"
+                    && e.tags == vec!["rust".to_string()]
+                    && e.content_warning.as_deref() == Some("ネタバレ")
+            })
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::publish_draft(
+            &drafts,
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            1,
+        )
+        .await;
+        assert_eq!(result, Ok(Some(Posted::Published)));
+    }
+
+    #[tokio::test]
+    async fn test_publish_draft_too_long() {
+        let mut drafts = MockDrafts::new();
+        drafts
+            .expect_find()
+            .returning(|id, account_id| Some(draft(id, account_id, &"あ".repeat(141))));
+        drafts.expect_delete().never();
+
+        let mut tweets = MockTweets::new();
+        tweets.expect_store().never();
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::publish_draft(
+            &drafts,
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            1,
+        )
+        .await;
+        assert_eq!(result, Err(TweetError::TooLong));
+    }
+}
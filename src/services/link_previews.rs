@@ -0,0 +1,55 @@
+use chrono::Utc;
+
+use crate::entities::LinkPreview;
+use crate::link_fetcher::LinkFetcher;
+use crate::opengraph;
+use crate::repositories::LinkPreviews;
+
+const FETCH_BATCH_SIZE: i64 = 10;
+
+/// Fetches the pages of newly posted links and caches their cards. A page that can't be
+/// fetched is cached without a card, so it isn't tried again until the cache expires.
+pub async fn fetch_link_previews(repo: &impl LinkPreviews, fetcher: &impl LinkFetcher) {
+    for url in repo.list_pending(FETCH_BATCH_SIZE).await {
+        let preview = match fetcher.fetch(&url).await {
+            Some(html) => opengraph::parse(&url, &html),
+            None => LinkPreview::empty(&url),
+        };
+        repo.store(&preview, Utc::now()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::link_fetcher::MockLinkFetcher;
+    use crate::repositories::MockLinkPreviews;
+
+    #[tokio::test]
+    async fn test_fetch_link_previews() {
+        let mut link_previews = MockLinkPreviews::new();
+        link_previews.expect_list_pending().returning(|_| {
+            vec![
+                "https://example.com/".to_string(),
+                "http://127.0.0.1/".to_string(),
+            ]
+        });
+        link_previews
+            .expect_store()
+            .withf(|e, _| e.url == "https://example.com/" && e.title == "Example")
+            .once()
+            .return_const(());
+        link_previews
+            .expect_store()
+            .withf(|e, _| e.url == "http://127.0.0.1/" && e.title.is_empty())
+            .once()
+            .return_const(());
+
+        let mut fetcher = MockLinkFetcher::new();
+        fetcher.expect_fetch().returning(|url| {
+            Some(r#"<meta property="og:title" content="Example">"#.to_string())
+                .filter(|_| url == "https://example.com/")
+        });
+
+        super::fetch_link_previews(&link_previews, &fetcher).await;
+    }
+}
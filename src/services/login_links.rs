@@ -0,0 +1,131 @@
+use chrono::{Duration, Utc};
+
+use crate::constants::base_url;
+use crate::entities::LoginLink;
+use crate::mailer::{Email, Mailer};
+use crate::repositories::{Accounts, LoginLinks};
+use crate::services::accounts::issue_session;
+use crate::services::SessionToken;
+use crate::token;
+
+const LOGIN_LINK_REQUEST_WINDOW_SECONDS: i64 = 900;
+const MAX_LOGIN_LINK_REQUESTS: i64 = 3;
+
+pub async fn request_login_link(
+    repo: &impl LoginLinks,
+    account_repo: &impl Accounts,
+    mailer: &impl Mailer,
+    email: &str,
+) {
+    let account = match account_repo.find_by(email).await {
+        Some(account) => account,
+        None => return,
+    };
+    let account_id = account.id().unwrap();
+
+    let since = Utc::now() - Duration::seconds(LOGIN_LINK_REQUEST_WINDOW_SECONDS);
+    if repo.count_since(account_id, since).await >= MAX_LOGIN_LINK_REQUESTS {
+        return;
+    }
+
+    let token = token::generate();
+    repo.store(&LoginLink::create(account_id, &token)).await;
+
+    let url = format!("{}/accounts/login_link?token={}", base_url(), token);
+    let email = Email {
+        to: account.email,
+        subject: "ログイン用リンク".to_string(),
+        body: format!(
+            "以下のリンクを開くとログインできます。\n\n{}\n\nこのリンクは15分間、1回だけ有効です。心当たりがない場合はこのメールを無視してください。",
+            url
+        ),
+    };
+    mailer.send(&email).await;
+}
+
+pub async fn create_session_from_login_link(
+    repo: &impl LoginLinks,
+    account_repo: &impl Accounts,
+    token: &str,
+) -> Option<SessionToken> {
+    let link = repo.consume(&token::digest(token), Utc::now()).await?;
+    let mut account = account_repo.find_by_id(link.account_id).await?;
+
+    // The link could only be opened from the inbox, which proves the address as well.
+    if !account.email_verified {
+        account.verify_email();
+        account_repo.store(&account).await;
+    }
+
+    Some(issue_session(account_repo, &mut account).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::Account;
+    use crate::mailer::MockMailer;
+    use crate::repositories::{MockAccounts, MockLoginLinks};
+    use crate::token;
+
+    fn account(id: i32) -> Account {
+        Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("password{}", id),
+            format!("display_name{}", id),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_request_login_link() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by().returning(|_| Some(account(1)));
+
+        let mut links = MockLoginLinks::new();
+        links.expect_count_since().returning(|_, _| 0);
+        links
+            .expect_store()
+            .withf(|e| e.account_id == 1 && e.expires_at > e.created_at)
+            .once()
+            .return_const(());
+
+        let mut mailer = MockMailer::new();
+        mailer
+            .expect_send()
+            .withf(|e| e.to == "1@example.com" && e.body.contains("/accounts/login_link?token="))
+            .once()
+            .return_const(());
+
+        super::request_login_link(&links, &accounts, &mailer, "1@example.com").await;
+    }
+
+    #[tokio::test]
+    async fn test_request_login_link_throttled() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by().returning(|_| Some(account(1)));
+
+        let mut links = MockLoginLinks::new();
+        links.expect_count_since().returning(|_, _| 3);
+        links.expect_store().never();
+
+        let mut mailer = MockMailer::new();
+        mailer.expect_send().never();
+
+        super::request_login_link(&links, &accounts, &mailer, "1@example.com").await;
+    }
+
+    #[tokio::test]
+    async fn test_create_session_from_used_login_link() {
+        let mut links = MockLoginLinks::new();
+        links
+            .expect_consume()
+            .withf(|hash, _| hash == token::digest("token"))
+            .returning(|_, _| None);
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().never();
+
+        let result = super::create_session_from_login_link(&links, &accounts, "token").await;
+        assert!(result.is_none());
+    }
+}
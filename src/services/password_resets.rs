@@ -0,0 +1,175 @@
+use chrono::Utc;
+
+use crate::constants::base_url;
+use crate::entities::PasswordReset;
+use crate::mailer::{Email, Mailer};
+use crate::repositories::{Accounts, PasswordResets, Sessions};
+use crate::token;
+
+pub async fn request_password_reset(
+    repo: &impl PasswordResets,
+    account_repo: &impl Accounts,
+    mailer: &impl Mailer,
+    email: &str,
+) {
+    let account = match account_repo.find_by(email).await {
+        Some(account) => account,
+        None => return,
+    };
+
+    let token = token::generate();
+    let reset = PasswordReset::create(account.id().unwrap(), &token);
+    repo.store(&reset).await;
+
+    let url = format!("{}/password/reset?token={}", base_url(), token);
+    let email = Email {
+        to: account.email,
+        subject: "パスワードの再設定".to_string(),
+        body: format!(
+            "以下のリンクを開いて新しいパスワードを設定してください。\n\n{}\n\nこのリンクの有効期限は1時間です。心当たりがない場合はこのメールを無視してください。",
+            url
+        ),
+    };
+    mailer.send(&email).await;
+}
+
+pub async fn reset_password(
+    repo: &impl PasswordResets,
+    account_repo: &impl Accounts,
+    session_repo: &impl Sessions,
+    token: &str,
+    password: &str,
+) -> bool {
+    // Used up in the same statement that checks it, so a token can't set the password twice.
+    let reset = match repo.consume(&token::digest(token), Utc::now()).await {
+        Some(reset) => reset,
+        None => return false,
+    };
+    let mut account = match account_repo.find_by_id(reset.account_id).await {
+        Some(account) => account,
+        None => return false,
+    };
+
+    account.set_password(password);
+    account_repo.store(&account).await;
+    session_repo.delete_all(reset.account_id).await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use std::sync::{Arc, Mutex};
+
+    use crate::entities::{Account, PasswordReset};
+    use crate::mailer::MockMailer;
+    use crate::repositories::{MockAccounts, MockPasswordResets, MockSessions};
+    use crate::token;
+
+    fn account(id: i32) -> Account {
+        Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("password{}", id),
+            format!("display_name{}", id),
+        )
+    }
+
+    fn password_reset(account_id: i32, token: &str) -> PasswordReset {
+        PasswordReset::new(
+            account_id,
+            token::digest(token),
+            Utc::now() + Duration::seconds(60),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_request_password_reset() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by().returning(|_| Some(account(1)));
+
+        let stored_hash = Arc::new(Mutex::new(String::new()));
+        let stored = stored_hash.clone();
+        let mut resets = MockPasswordResets::new();
+        resets
+            .expect_store()
+            .withf(|e| e.account_id == 1)
+            .once()
+            .returning(move |e| *stored.lock().unwrap() = e.token_hash.clone());
+
+        let sent_token = Arc::new(Mutex::new(String::new()));
+        let sent = sent_token.clone();
+        let mut mailer = MockMailer::new();
+        mailer
+            .expect_send()
+            .withf(|e| e.to == "1@example.com")
+            .once()
+            .returning(move |e| {
+                let url = e.body.lines().find(|x| x.contains("token=")).unwrap();
+                *sent.lock().unwrap() = url.split("token=").nth(1).unwrap().to_string();
+            });
+
+        super::request_password_reset(&resets, &accounts, &mailer, "1@example.com").await;
+        let sent_token = sent_token.lock().unwrap().clone();
+        assert_eq!(token::digest(&sent_token), *stored_hash.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_request_password_reset_not_found() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by().returning(|_| None);
+        let mut resets = MockPasswordResets::new();
+        resets.expect_store().never();
+        let mut mailer = MockMailer::new();
+        mailer.expect_send().never();
+
+        super::request_password_reset(&resets, &accounts, &mailer, "1@example.com").await;
+    }
+
+    #[tokio::test]
+    async fn test_reset_password() {
+        let mut resets = MockPasswordResets::new();
+        resets
+            .expect_consume()
+            .withf(|hash, _| hash == token::digest("token"))
+            .once()
+            .returning(|_, _| Some(password_reset(1, "token")));
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| e.id() == Some(1) && e.matches_password("new password"))
+            .once()
+            .return_const(());
+
+        let mut sessions = MockSessions::new();
+        sessions
+            .expect_delete_all()
+            .withf(|id| *id == 1)
+            .once()
+            .return_const(());
+
+        let result =
+            super::reset_password(&resets, &accounts, &sessions, "token", "new password").await;
+        assert!(result);
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_used() {
+        let mut resets = MockPasswordResets::new();
+        resets.expect_consume().returning(|_, _| None);
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_store().never();
+
+        let mut sessions = MockSessions::new();
+        sessions.expect_delete_all().never();
+
+        let result =
+            super::reset_password(&resets, &accounts, &sessions, "token", "new password").await;
+        assert!(!result);
+    }
+}
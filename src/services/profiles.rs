@@ -0,0 +1,279 @@
+use chrono::{Duration, Utc};
+
+use crate::repositories::{Accounts, HandleRedirects, Tweets};
+use crate::request::UserContext;
+use crate::services::tweets::to_views;
+use crate::views::{AccountDeleted, Profile};
+
+pub enum ProfileLookup {
+    Found(Box<Profile>),
+    /// The handle used to belong to an account that has since been renamed.
+    Moved(String),
+    Deleted(AccountDeleted),
+    NotFound,
+}
+
+pub async fn profile(
+    repo: &impl Accounts,
+    redirect_repo: &impl HandleRedirects,
+    tweet_repo: &impl Tweets,
+    user_context: &UserContext,
+    edit_window: Duration,
+    handle: &str,
+) -> ProfileLookup {
+    let account = match repo.find_by_handle(handle).await {
+        Some(account) => account,
+        None => {
+            let moved_to = match redirect_repo.find(handle, Utc::now()).await {
+                Some(account_id) => repo.find_by_id(account_id).await,
+                None => None,
+            };
+            return match moved_to {
+                Some(account) if account.is_deleted() => deleted(handle),
+                Some(account) => ProfileLookup::Moved(account.handle),
+                None => ProfileLookup::NotFound,
+            };
+        }
+    };
+    if account.is_deleted() {
+        return deleted(&account.handle);
+    }
+
+    let tweets = tweet_repo.list_by(account.id().unwrap()).await;
+    let mut tweets = to_views(tweets, repo, user_context, edit_window).await;
+    let pinned = match account.pinned_tweet_id {
+        Some(id) => tweet_repo.find(id).await,
+        None => None,
+    };
+    let mut pinned = to_views(
+        pinned.into_iter().collect(),
+        repo,
+        user_context,
+        edit_window,
+    )
+    .await
+    .pop();
+    if let Some(pinned) = &mut pinned {
+        pinned.pinned = true;
+        for tweet in tweets.iter_mut().filter(|x| x.id == pinned.id) {
+            tweet.pinned = true;
+        }
+    }
+    ProfileLookup::Found(Box::new(Profile {
+        display_name: account.display_name.clone(),
+        handle: account.handle.clone(),
+        pinned,
+        tweets,
+    }))
+}
+
+fn deleted(handle: &str) -> ProfileLookup {
+    ProfileLookup::Deleted(AccountDeleted {
+        handle: handle.to_string(),
+    })
+}
+
+/// Pins one of the user's own tweets in place of the one pinned before. Returns the user's
+/// handle, for going back to the profile.
+pub async fn pin_tweet(
+    repo: &impl Accounts,
+    tweet_repo: &impl Tweets,
+    user_context: &UserContext,
+    id: i32,
+) -> String {
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    let tweet = tweet_repo.find(id).await;
+    if tweet
+        .filter(|x| x.posted_by == user_context.user_id)
+        .is_some()
+    {
+        account.pinned_tweet_id = Some(id);
+        repo.store(&account).await;
+    }
+    account.handle
+}
+
+pub async fn unpin_tweet(repo: &impl Accounts, user_context: &UserContext) -> String {
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    if account.pinned_tweet_id.is_some() {
+        account.pinned_tweet_id = None;
+        repo.store(&account).await;
+    }
+    account.handle
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::ProfileLookup;
+    use crate::entities::{Account, Tweet};
+    use crate::repositories::{MockAccounts, MockHandleRedirects, MockTweets};
+    use crate::request::UserContext;
+
+    fn account(id: i32) -> Account {
+        let mut account = Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("password{}", id),
+            format!("display_name{}", id),
+        );
+        account.handle = format!("handle{}", id);
+        account
+    }
+
+    fn tweet(id: i32, account_id: i32) -> Tweet {
+        Tweet::new(
+            id,
+            format!("message{}", id),
+            Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            account_id,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_profile() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_handle()
+            .returning(|_| Some(account(1)));
+        accounts
+            .expect_find()
+            .returning(|ids| ids.into_iter().map(|id| (id, account(id))).collect());
+
+        let mut tweets = MockTweets::new();
+        tweets.expect_list_by().returning(|id| {
+            vec![Tweet::new(
+                1,
+                "message1".to_string(),
+                Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+                id,
+            )]
+        });
+
+        let result = super::profile(
+            &accounts,
+            &MockHandleRedirects::new(),
+            &tweets,
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+            "HANDLE1",
+        )
+        .await;
+        match result {
+            ProfileLookup::Found(profile) => {
+                assert_eq!(profile.handle, "handle1");
+                assert_eq!(profile.tweets.len(), 1);
+                assert_eq!(profile.tweets[0].handle, "handle1");
+            }
+            _ => panic!("profile not found"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_profile_pinned() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_handle().returning(|_| {
+            let mut account = account(1);
+            account.pinned_tweet_id = Some(1);
+            Some(account)
+        });
+        accounts
+            .expect_find()
+            .returning(|ids| ids.into_iter().map(|id| (id, account(id))).collect());
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_list_by()
+            .returning(|id| vec![tweet(2, id), tweet(1, id)]);
+        tweets.expect_find().returning(|id| Some(tweet(id, 1)));
+
+        let result = super::profile(
+            &accounts,
+            &MockHandleRedirects::new(),
+            &tweets,
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+            "handle1",
+        )
+        .await;
+        match result {
+            ProfileLookup::Found(profile) => {
+                let pinned = profile.pinned.unwrap();
+                assert_eq!(pinned.id, "1");
+                assert!(pinned.pinned && pinned.own);
+                assert!(!profile.tweets[0].pinned);
+                assert!(profile.tweets[1].pinned);
+            }
+            _ => panic!("profile not found"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pin_tweet() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| e.pinned_tweet_id == Some(1))
+            .once()
+            .return_const(());
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_find()
+            .returning(|id| Some(tweet(id, if id == 1 { 1 } else { 2 })));
+
+        let user_context = UserContext { user_id: 1 };
+        let handle = super::pin_tweet(&accounts, &tweets, &user_context, 1).await;
+        assert_eq!(handle, "handle1");
+        // Someone else's tweet.
+        super::pin_tweet(&accounts, &tweets, &user_context, 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_profile_moved() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_handle().returning(|_| None);
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let mut redirects = MockHandleRedirects::new();
+        redirects.expect_find().returning(|_, _| Some(2));
+
+        let result = super::profile(
+            &accounts,
+            &redirects,
+            &MockTweets::new(),
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+            "old",
+        )
+        .await;
+        assert!(matches!(result, ProfileLookup::Moved(handle) if handle == "handle2"));
+    }
+
+    #[tokio::test]
+    async fn test_profile_deleted() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_handle().returning(|_| {
+            let mut account = account(1);
+            account.erase(Utc::now());
+            Some(account)
+        });
+
+        let result = super::profile(
+            &accounts,
+            &MockHandleRedirects::new(),
+            &MockTweets::new(),
+            &UserContext { user_id: 2 },
+            Duration::minutes(30),
+            "Handle1",
+        )
+        .await;
+        assert!(matches!(result, ProfileLookup::Deleted(page) if page.handle == "handle1"));
+    }
+}
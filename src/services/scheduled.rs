@@ -0,0 +1,285 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+
+use crate::entities::Tweet;
+use crate::repositories::{Accounts, Tweets};
+use crate::request::UserContext;
+use crate::services::tweets::extract_details;
+use crate::services::TweetError;
+use crate::tweet_length::{self, MAX_LENGTH};
+use crate::views::{PendingTweet, Scheduled, ScheduledEdit, ScheduledTweet};
+
+/// Reads the value of a `datetime-local` input in the user's time zone, which the browser sends
+/// as minutes east of UTC. Without one the time is taken as UTC. An empty value means now.
+pub fn parse_publish_at(
+    local: &str,
+    utc_offset: &str,
+) -> Result<Option<DateTime<Utc>>, TweetError> {
+    let local = local.trim();
+    if local.is_empty() {
+        return Ok(None);
+    }
+    let naive = NaiveDateTime::parse_from_str(local, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(local, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|_| TweetError::InvalidSchedule)?;
+    let minutes = match utc_offset.trim() {
+        "" => 0,
+        x => x.parse::<i32>().map_err(|_| TweetError::InvalidSchedule)?,
+    };
+    let offset = minutes
+        .checked_mul(60)
+        .and_then(FixedOffset::east_opt)
+        .ok_or(TweetError::InvalidSchedule)?;
+    offset
+        .from_local_datetime(&naive)
+        .single()
+        .map(|x| Some(x.with_timezone(&Utc)))
+        .ok_or(TweetError::InvalidSchedule)
+}
+
+pub async fn list_scheduled(repo: &impl Tweets, user_context: &UserContext) -> Scheduled {
+    let tweets = repo.list_scheduled_by(user_context.user_id).await;
+    Scheduled {
+        tweets: tweets.into_iter().map(|x| x.into()).collect(),
+    }
+}
+
+pub async fn scheduled_edit_form(
+    repo: &impl Tweets,
+    user_context: &UserContext,
+    id: i32,
+    error: &str,
+) -> Option<ScheduledEdit> {
+    let tweet = find_own(repo, user_context, id).await?;
+    Some(ScheduledEdit {
+        id,
+        message: tweet.message,
+        publish_at: tweet
+            .publish_at
+            .map(|x| x.format("%Y-%m-%dT%H:%M").to_string())
+            .unwrap_or_default(),
+        error: error.to_string(),
+    })
+}
+
+/// Changes the message or the time of a tweet that hasn't been published yet. Nobody has seen
+/// it, so unlike editing a published tweet this keeps no revision.
+pub async fn edit_scheduled_tweet(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    id: i32,
+    message: &str,
+    publish_at: Option<DateTime<Utc>>,
+) -> Result<(), TweetError> {
+    let mut tweet = find_own(repo, user_context, id)
+        .await
+        .ok_or(TweetError::NotEditable)?;
+    if tweet_length::weighted_length(message) > MAX_LENGTH {
+        return Err(TweetError::TooLong);
+    }
+    let publish_at = publish_at
+        .filter(|x| *x > Utc::now())
+        .ok_or(TweetError::InvalidSchedule)?;
+    if tweet.message != message {
+        tweet.message = message.into();
+        extract_details(&mut tweet, account_repo).await;
+    }
+    tweet.publish_at = Some(publish_at);
+    repo.store(&tweet).await;
+    Ok(())
+}
+
+/// Moves the tweet to the trash, from where it can still be restored. This is also how a tweet
+/// held back for the undo delay is taken back. Returns whether it was still unpublished.
+pub async fn cancel_scheduled_tweet(
+    repo: &impl Tweets,
+    user_context: &UserContext,
+    id: i32,
+) -> bool {
+    repo.cancel_scheduled(id, user_context.user_id, Utc::now())
+        .await
+}
+
+/// The tweet the user just posted, if it is still held back for the undo delay.
+pub async fn pending_tweet(
+    repo: &impl Tweets,
+    user_context: &UserContext,
+    id: i32,
+) -> Option<PendingTweet> {
+    let tweet = find_own(repo, user_context, id).await?;
+    let seconds_left = (tweet.publish_at? - Utc::now()).num_seconds();
+    if seconds_left > 0 {
+        Some(PendingTweet { id, seconds_left })
+    } else {
+        None
+    }
+}
+
+pub async fn publish_scheduled_tweets(repo: &impl Tweets) {
+    let count = repo.publish_due(Utc::now()).await;
+    if count > 0 {
+        tracing::info!("published {} scheduled tweets", count);
+    }
+}
+
+async fn find_own(repo: &impl Tweets, user_context: &UserContext, id: i32) -> Option<Tweet> {
+    repo.find_scheduled(id)
+        .await
+        .filter(|x| x.posted_by == user_context.user_id)
+}
+
+impl From<Tweet> for ScheduledTweet {
+    fn from(e: Tweet) -> Self {
+        let publish_at = e.publish_at.unwrap_or_else(Utc::now);
+        ScheduledTweet {
+            id: e.id().unwrap_or(-1),
+            message: e.message,
+            image_count: e.media.len(),
+            publish_at: publish_at.to_rfc3339(),
+            publish_at_utc: publish_at.format("%Y/%m/%d %H:%M").to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::entities::Tweet;
+    use crate::repositories::{MockAccounts, MockTweets};
+    use crate::request::UserContext;
+    use crate::services::TweetError;
+
+    fn scheduled_tweet(id: i32, account_id: i32) -> Tweet {
+        let mut tweet = Tweet::new(
+            id,
+            format!("message{}", id),
+            Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            account_id,
+        );
+        tweet.publish_at = Some(Utc::now() + Duration::hours(1));
+        tweet
+    }
+
+    #[test]
+    fn test_parse_publish_at() {
+        assert_eq!(
+            super::parse_publish_at("2026-01-01T09:30", "540"),
+            Ok(Some(Utc.ymd(2026, 1, 1).and_hms(0, 30, 0)))
+        );
+        assert_eq!(
+            super::parse_publish_at("2026-01-01T09:30:15", "-300"),
+            Ok(Some(Utc.ymd(2026, 1, 1).and_hms(14, 30, 15)))
+        );
+        assert_eq!(
+            super::parse_publish_at("2026-01-01T09:30", ""),
+            Ok(Some(Utc.ymd(2026, 1, 1).and_hms(9, 30, 0)))
+        );
+        assert_eq!(super::parse_publish_at(" ", "540"), Ok(None));
+        assert_eq!(
+            super::parse_publish_at("2026-01-01 09:30", "0"),
+            Err(TweetError::InvalidSchedule)
+        );
+        assert_eq!(
+            super::parse_publish_at("2026-01-01T09:30", "100000"),
+            Err(TweetError::InvalidSchedule)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_edit_scheduled_tweet() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_find_scheduled()
+            .returning(|id| Some(scheduled_tweet(id, 1)));
+        tweets
+            .expect_store()
+            .withf(|e| {
+                e.message == "#rust"
+                    && e.tags == vec!["rust".to_string()]
+                    && e.edited_at.is_none()
+                    && e.publish_at.unwrap() > Utc::now() + Duration::hours(2)
+            })
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_handle().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::edit_scheduled_tweet(
+            &tweets,
+            &accounts,
+            &user_context,
+            1,
+            "#rust",
+            Some(Utc::now() + Duration::hours(3)),
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_edit_scheduled_tweet_invalid() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_find_scheduled()
+            .returning(|id| Some(scheduled_tweet(id, if id == 1 { 1 } else { 2 })));
+        tweets.expect_store().never();
+
+        let accounts = MockAccounts::new();
+        let user_context = UserContext { user_id: 1 };
+        let past = Some(Utc::now() - Duration::minutes(1));
+        let result =
+            super::edit_scheduled_tweet(&tweets, &accounts, &user_context, 1, "a", past).await;
+        assert_eq!(result, Err(TweetError::InvalidSchedule));
+
+        let future = Some(Utc::now() + Duration::hours(1));
+        let result =
+            super::edit_scheduled_tweet(&tweets, &accounts, &user_context, 2, "a", future).await;
+        assert_eq!(result, Err(TweetError::NotEditable));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_scheduled_tweet() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_cancel_scheduled()
+            .withf(|id, account_id, _| *id == 1 && *account_id == 1)
+            .once()
+            .return_const(true);
+
+        let user_context = UserContext { user_id: 1 };
+        assert!(super::cancel_scheduled_tweet(&tweets, &user_context, 1).await);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_scheduled_tweet_published() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_cancel_scheduled().return_const(false);
+        tweets.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        assert!(!super::cancel_scheduled_tweet(&tweets, &user_context, 1).await);
+    }
+
+    #[tokio::test]
+    async fn test_pending_tweet() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_find_scheduled().returning(|id| {
+            let mut tweet = scheduled_tweet(id, 1);
+            tweet.publish_at = Some(Utc::now() + Duration::seconds(if id == 1 { 20 } else { -1 }));
+            Some(tweet)
+        });
+
+        let user_context = UserContext { user_id: 1 };
+        let pending = super::pending_tweet(&tweets, &user_context, 1)
+            .await
+            .unwrap();
+        assert!(pending.seconds_left > 15 && pending.seconds_left <= 20);
+        // Due, so about to be published.
+        assert!(super::pending_tweet(&tweets, &user_context, 2)
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,266 @@
+use chrono::Duration;
+
+use crate::entities::{Account, SavedSearch, SearchQuery};
+use crate::repositories::{Accounts, SavedSearches, SearchOrder, Tweets};
+use crate::request::UserContext;
+use crate::services::tweets::to_views;
+use crate::text;
+use crate::views::{AccountSearch, AccountSuggestion, SavedSearchLink, Search};
+
+/// Counted across words, phrases and operators alike, since each one is a condition to check.
+const MAX_SEARCH_TERMS: usize = 5;
+const MAX_SAVED_SEARCHES: usize = 25;
+const ACCOUNT_SEARCH_LIMIT: i64 = 50;
+const TYPEAHEAD_LIMIT: i64 = 8;
+
+pub async fn search_tweets(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    saved_repo: &impl SavedSearches,
+    user_context: &UserContext,
+    edit_window: Duration,
+    query: &str,
+    order: SearchOrder,
+) -> Search {
+    let query = query.trim();
+    let mut search_query = SearchQuery::parse(query);
+    search_query.clauses.truncate(MAX_SEARCH_TERMS);
+    let terms = search_query.terms();
+    let tweets = repo.search(search_query, order).await;
+    let mut tweets = to_views(tweets, account_repo, user_context, edit_window).await;
+    for tweet in tweets.iter_mut() {
+        tweet.highlight(&terms);
+    }
+
+    let saved = saved_repo.list(user_context.user_id).await;
+    Search {
+        query: query.to_string(),
+        recency: order == SearchOrder::Recency,
+        saved_query: saved.iter().any(|x| x.query == query),
+        saved: saved.into_iter().map(|x| x.into()).collect(),
+        tweets,
+    }
+}
+
+pub async fn save_search(saved_repo: &impl SavedSearches, user_context: &UserContext, query: &str) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+    let saved = saved_repo.list(user_context.user_id).await;
+    if saved.len() >= MAX_SAVED_SEARCHES {
+        return;
+    }
+    saved_repo
+        .store(&SavedSearch::create(user_context.user_id, query))
+        .await;
+}
+
+pub async fn delete_saved_search(
+    saved_repo: &impl SavedSearches,
+    user_context: &UserContext,
+    id: i32,
+) {
+    saved_repo.delete(id, user_context.user_id).await;
+}
+
+pub async fn search_accounts(repo: &impl Accounts, query: &str) -> AccountSearch {
+    let query = query.trim();
+    AccountSearch {
+        query: query.to_string(),
+        accounts: find_accounts(repo, query, ACCOUNT_SEARCH_LIMIT).await,
+    }
+}
+
+/// For autocompleting `@` mentions while typing, so it takes a partial handle.
+pub async fn suggest_accounts(repo: &impl Accounts, prefix: &str) -> Vec<AccountSuggestion> {
+    find_accounts(repo, prefix, TYPEAHEAD_LIMIT).await
+}
+
+async fn find_accounts(repo: &impl Accounts, query: &str, limit: i64) -> Vec<AccountSuggestion> {
+    let prefix = text::normalize(query.trim().trim_start_matches('@'));
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    repo.search(&prefix, limit)
+        .await
+        .into_iter()
+        .map(|x| x.into())
+        .collect()
+}
+
+impl From<Account> for AccountSuggestion {
+    fn from(e: Account) -> Self {
+        AccountSuggestion {
+            handle: e.handle,
+            display_name: e.display_name,
+        }
+    }
+}
+
+impl From<SavedSearch> for SavedSearchLink {
+    fn from(e: SavedSearch) -> Self {
+        SavedSearchLink {
+            id: e.id().unwrap_or(-1).to_string(),
+            href: format!("/search?q={}", urlencoding::encode(&e.query)),
+            query: e.query,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+    use std::collections::HashMap;
+
+    use crate::entities::{Account, Clause, SavedSearch, SearchQuery, Tweet};
+    use crate::repositories::{MockAccounts, MockSavedSearches, MockTweets, SearchOrder};
+    use crate::request::UserContext;
+
+    fn account(id: i32) -> Account {
+        let mut account = Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("password{}", id),
+            format!("display_name{}", id),
+        );
+        account.handle = format!("handle{}", id);
+        account
+    }
+
+    fn tweet(id: i32, message: &str) -> Tweet {
+        Tweet::new(
+            id,
+            message.to_string(),
+            Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            1,
+        )
+    }
+
+    #[test]
+    fn test_parse_search_query() {
+        let query = SearchQuery::parse(
+            r#"rust "東京 タワー" -java from:@Taro to:hanako since:2026-01-01 until:2026-02-01 has:links until:tomorrow"#,
+        );
+        assert_eq!(
+            query.clauses,
+            vec![
+                Clause::Contains("rust".to_string()),
+                Clause::Contains("東京 タワー".to_string()),
+                Clause::Excludes("java".to_string()),
+                Clause::From("Taro".to_string()),
+                Clause::To("hanako".to_string()),
+                Clause::Since(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()),
+                Clause::Until(NaiveDate::from_ymd_opt(2026, 2, 1).unwrap()),
+                Clause::HasLinks,
+                Clause::Contains("until:tomorrow".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_tweets() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_search()
+            .withf(|query, order| {
+                query.terms() == vec!["rust".to_string(), "東京".to_string()]
+                    && *order == SearchOrder::Relevance
+            })
+            .returning(|_, _| vec![tweet(1, "東京でRust")]);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find()
+            .returning(|ids| ids.into_iter().map(|id| (id, account(id))).collect());
+
+        let mut saved = MockSavedSearches::new();
+        saved
+            .expect_list()
+            .returning(|id| vec![SavedSearch::new(1, id, "rust 東京".to_string())]);
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::search_tweets(
+            &tweets,
+            &accounts,
+            &saved,
+            &user_context,
+            Duration::minutes(30),
+            " rust  東京 ",
+            SearchOrder::Relevance,
+        )
+        .await;
+        let segments = &result.tweets[0].blocks[0].lines[0];
+        assert_eq!(segments.len(), 3);
+        assert!(segments[0].highlighted && segments[0].text == "東京");
+        assert!(!segments[1].highlighted);
+        assert!(segments[2].highlighted && segments[2].text == "Rust");
+        assert!(!result.saved_query);
+        assert_eq!(result.saved[0].href, "/search?q=rust%20%E6%9D%B1%E4%BA%AC");
+    }
+
+    #[tokio::test]
+    async fn test_search_tweets_too_many_terms() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_search()
+            .withf(|query, _| {
+                query.clauses.len() == 5 && query.clauses[4] == Clause::Contains("c".to_string())
+            })
+            .returning(|_, _| Vec::new());
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find().returning(|_| HashMap::new());
+
+        let mut saved = MockSavedSearches::new();
+        saved.expect_list().returning(|_| Vec::new());
+
+        let user_context = UserContext { user_id: 1 };
+        super::search_tweets(
+            &tweets,
+            &accounts,
+            &saved,
+            &user_context,
+            Duration::minutes(30),
+            "from:taro -a b has:links c e f -g",
+            SearchOrder::Recency,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_suggest_accounts() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_search()
+            .withf(|prefix, limit| prefix == "taro" && *limit == 8)
+            .returning(|_, _| vec![account(1)]);
+
+        let result = super::suggest_accounts(&accounts, "@ＴＡＲＯ").await;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].handle, "handle1");
+    }
+
+    #[tokio::test]
+    async fn test_suggest_accounts_empty() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_search().never();
+
+        let result = super::suggest_accounts(&accounts, "@").await;
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_search_limit() {
+        let mut saved = MockSavedSearches::new();
+        saved.expect_list().returning(|id| {
+            (0..25)
+                .map(|x| SavedSearch::new(x, id, format!("query{}", x)))
+                .collect()
+        });
+        saved.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        super::save_search(&saved, &user_context, "rust").await;
+    }
+}
@@ -0,0 +1,383 @@
+use chrono::{Duration, Utc};
+
+use crate::entities::Account;
+use crate::mailer::{Email, Mailer};
+use crate::repositories::{Accounts, HandleRedirects, Sessions};
+use crate::request::UserContext;
+use crate::services::accounts::{
+    handle_available, issue_verified_session, send_verification_email,
+};
+use crate::services::SessionToken;
+use crate::views::Settings;
+
+const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+const HANDLE_REDIRECT_DAYS: i64 = 30;
+
+#[derive(Debug, PartialEq)]
+pub enum SettingsError {
+    InvalidDisplayName,
+    InvalidHandle,
+    HandleTaken,
+    InvalidPassword,
+    InvalidEmail,
+    EmailTaken,
+    InvalidUndoSendDelay,
+}
+
+pub async fn settings(repo: &impl Accounts, user_context: &UserContext) -> Settings {
+    let account = repo.find_by_id(user_context.user_id).await.unwrap();
+    Settings {
+        display_name: account.display_name.clone(),
+        handle: account.handle.clone(),
+        email: account.email.clone(),
+        email_verified: account.email_verified,
+        two_factor_enabled: account.two_factor_enabled(),
+        undo_send_seconds: account.undo_send_seconds,
+        always_expand_content_warnings: account.always_expand_content_warnings,
+        notice: String::new(),
+        error: String::new(),
+    }
+}
+
+pub async fn change_display_name(
+    repo: &impl Accounts,
+    user_context: &UserContext,
+    display_name: &str,
+) -> Result<(), SettingsError> {
+    let display_name = display_name.trim();
+    if display_name.is_empty() || display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+        return Err(SettingsError::InvalidDisplayName);
+    }
+
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    account.display_name = display_name.to_string();
+    repo.store(&account).await;
+    Ok(())
+}
+
+/// Zero posts new tweets right away.
+pub async fn change_undo_send_delay(
+    repo: &impl Accounts,
+    user_context: &UserContext,
+    seconds: i32,
+) -> Result<(), SettingsError> {
+    if !Account::is_valid_undo_send_seconds(seconds) {
+        return Err(SettingsError::InvalidUndoSendDelay);
+    }
+
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    account.undo_send_seconds = seconds;
+    repo.store(&account).await;
+    Ok(())
+}
+
+pub async fn change_content_warning_display(
+    repo: &impl Accounts,
+    user_context: &UserContext,
+    always_expand: bool,
+) {
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    account.always_expand_content_warnings = always_expand;
+    repo.store(&account).await;
+}
+
+/// The old handle keeps redirecting to the account, and can't be taken by anyone else, for a while.
+pub async fn change_handle(
+    repo: &impl Accounts,
+    redirect_repo: &impl HandleRedirects,
+    user_context: &UserContext,
+    handle: &str,
+) -> Result<(), SettingsError> {
+    let handle = handle.trim().trim_start_matches('@');
+    if !Account::is_valid_handle(handle) {
+        return Err(SettingsError::InvalidHandle);
+    }
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    if handle == account.handle {
+        return Ok(());
+    }
+    if !handle_available(repo, redirect_repo, handle, account.id()).await {
+        return Err(SettingsError::HandleTaken);
+    }
+
+    let old_handle = std::mem::replace(&mut account.handle, handle.to_string());
+    repo.store(&account).await;
+    let expires_at = Utc::now() + Duration::days(HANDLE_REDIRECT_DAYS);
+    redirect_repo
+        .store(&old_handle, user_context.user_id, expires_at)
+        .await;
+    Ok(())
+}
+
+/// The new address has to be verified again before the account can post.
+pub async fn change_email(
+    repo: &impl Accounts,
+    mailer: &impl Mailer,
+    user_context: &UserContext,
+    email: &str,
+    password: &str,
+) -> Result<(), SettingsError> {
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    if !account.matches_password(password) {
+        return Err(SettingsError::InvalidPassword);
+    }
+    let email = email.trim();
+    if email == account.email {
+        return Ok(());
+    }
+    if !Account::is_valid_email(email) {
+        return Err(SettingsError::InvalidEmail);
+    }
+    if repo.find_by(email).await.is_some() {
+        return Err(SettingsError::EmailTaken);
+    }
+
+    let old_email = account.email.clone();
+    account.change_email(email);
+    repo.store(&account).await;
+
+    send_verification_email(mailer, &account).await;
+    let notice = Email {
+        to: old_email,
+        subject: "メールアドレスが変更されました".to_string(),
+        body: format!(
+            "アカウントのメールアドレスが {} に変更されました。心当たりがない場合はお問い合わせください。",
+            email
+        ),
+    };
+    mailer.send(&notice).await;
+    Ok(())
+}
+
+/// Signs out every other session and returns a fresh one for the current browser.
+pub async fn change_password(
+    repo: &impl Accounts,
+    session_repo: &impl Sessions,
+    user_context: &UserContext,
+    current_password: &str,
+    new_password: &str,
+) -> Result<SessionToken, SettingsError> {
+    let mut account = repo.find_by_id(user_context.user_id).await.unwrap();
+    if !account.matches_password(current_password) {
+        return Err(SettingsError::InvalidPassword);
+    }
+
+    account.set_password(new_password);
+    repo.store(&account).await;
+    session_repo.delete_all(user_context.user_id).await;
+    Ok(issue_verified_session(user_context.user_id).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::SettingsError;
+    use crate::entities::Account;
+    use crate::mailer::MockMailer;
+    use crate::repositories::{MockAccounts, MockHandleRedirects, MockSessions};
+    use crate::request::UserContext;
+
+    fn account(id: i32) -> Account {
+        let mut account = Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("{:x}", Sha256::digest(format!("password{}", id).as_bytes())),
+            format!("display_name{}", id),
+        );
+        account.handle = format!("handle{}", id);
+        account.email_verified = true;
+        account
+    }
+
+    #[tokio::test]
+    async fn test_change_display_name() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| e.id() == Some(1) && e.display_name == "Taro")
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_display_name(&accounts, &user_context, " Taro ").await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_change_display_name_empty() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_display_name(&accounts, &user_context, "  ").await;
+        assert_eq!(result, Err(SettingsError::InvalidDisplayName));
+    }
+
+    #[tokio::test]
+    async fn test_change_undo_send_delay() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| e.undo_send_seconds == 10)
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_undo_send_delay(&accounts, &user_context, 10).await;
+        assert_eq!(result, Ok(()));
+        let result = super::change_undo_send_delay(&accounts, &user_context, 31).await;
+        assert_eq!(result, Err(SettingsError::InvalidUndoSendDelay));
+    }
+
+    #[tokio::test]
+    async fn test_change_handle() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_find_by_handle().returning(|_| None);
+        accounts
+            .expect_store()
+            .withf(|e| e.handle == "taro")
+            .once()
+            .return_const(());
+
+        let mut redirects = MockHandleRedirects::new();
+        redirects.expect_find().returning(|_, _| None);
+        redirects
+            .expect_store()
+            .withf(|handle, account_id, _| handle == "handle1" && *account_id == 1)
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_handle(&accounts, &redirects, &user_context, "@taro").await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_change_handle_reserved_by_redirect() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_find_by_handle().returning(|_| None);
+        accounts.expect_store().never();
+
+        let mut redirects = MockHandleRedirects::new();
+        redirects.expect_find().returning(|_, _| Some(2));
+        redirects.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_handle(&accounts, &redirects, &user_context, "handle2").await;
+        assert_eq!(result, Err(SettingsError::HandleTaken));
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_find_by().returning(|_| None);
+        accounts
+            .expect_store()
+            .withf(|e| e.email == "new@example.com" && !e.email_verified)
+            .once()
+            .return_const(());
+
+        let mut mailer = MockMailer::new();
+        mailer
+            .expect_send()
+            .withf(|e| e.to == "new@example.com" && e.body.contains("/accounts/verify?token="))
+            .once()
+            .return_const(());
+        mailer
+            .expect_send()
+            .withf(|e| e.to == "1@example.com")
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_email(
+            &accounts,
+            &mailer,
+            &user_context,
+            "new@example.com",
+            "password1",
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_change_email_taken() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_find_by().returning(|_| Some(account(2)));
+        accounts.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_email(
+            &accounts,
+            &MockMailer::new(),
+            &user_context,
+            "2@example.com",
+            "password1",
+        )
+        .await;
+        assert_eq!(result, Err(SettingsError::EmailTaken));
+    }
+
+    #[tokio::test]
+    async fn test_change_email_invalid() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_email(
+            &accounts,
+            &MockMailer::new(),
+            &user_context,
+            "not an address",
+            "password1",
+        )
+        .await;
+        assert_eq!(result, Err(SettingsError::InvalidEmail));
+    }
+
+    #[tokio::test]
+    async fn test_change_password_wrong_current_password() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_store().never();
+
+        let mut sessions = MockSessions::new();
+        sessions.expect_delete_all().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::change_password(
+            &accounts,
+            &sessions,
+            &user_context,
+            "password2",
+            "new password",
+        )
+        .await;
+        assert_eq!(result.err(), Some(SettingsError::InvalidPassword));
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::Duration;
+
+use crate::media_store::MediaStore;
+use crate::repositories::{Accounts, Tweets};
+use crate::request::UserContext;
+use crate::services::tweets::{build_tweet, posting_account, to_views};
+use crate::services::{NewTweet, TweetError};
+use crate::text;
+use crate::tweet_length::{weighted_length, MAX_LENGTH};
+use crate::views::Thread;
+
+const MAX_THREAD_LENGTH: usize = 25;
+/// Left free in each tweet for the longest numbering, " 25/25".
+const NUMBERING_ROOM: usize = 6;
+
+/// Posts the segments as a thread. Segments over the limit are split between sentences, and
+/// `numbered` adds "1/n" to each tweet, and the content warning, if not empty, goes on every
+/// tweet. Every tweet is checked like `create_tweet` does before any is stored, so the thread is
+/// posted whole or not at all. Threads aren't held back for the undo delay.
+pub async fn create_thread(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    media_store: &impl MediaStore,
+    user_context: &UserContext,
+    segments: &[String],
+    numbered: bool,
+    content_warning: &str,
+) -> Result<(), TweetError> {
+    let account = posting_account(account_repo, user_context).await?;
+    let limit = if numbered {
+        MAX_LENGTH - NUMBERING_ROOM
+    } else {
+        MAX_LENGTH
+    };
+    let mut messages = segments
+        .iter()
+        .flat_map(|x| split(x, limit))
+        .collect::<Vec<String>>();
+    if messages.is_empty() {
+        return Err(TweetError::EmptyThread);
+    }
+    if messages.len() > MAX_THREAD_LENGTH {
+        return Err(TweetError::ThreadTooLong);
+    }
+    if numbered {
+        let count = messages.len();
+        for (i, message) in messages.iter_mut().enumerate() {
+            message.push_str(&format!(" {}/{}", i + 1, count));
+        }
+    }
+
+    let mut tweets = Vec::new();
+    for message in messages {
+        let new_tweet = NewTweet {
+            message,
+            content_warning: content_warning.to_string(),
+            ..Default::default()
+        };
+        let tweet = build_tweet(&account, account_repo, media_store, new_tweet).await?;
+        tweets.push(tweet);
+    }
+    repo.store_thread(&tweets).await;
+    Ok(())
+}
+
+pub async fn thread(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    edit_window: Duration,
+    id: i32,
+) -> Option<Thread> {
+    let tweets = repo.list_thread(id).await;
+    if tweets.is_empty() {
+        return None;
+    }
+    Some(Thread {
+        tweets: to_views(tweets, account_repo, user_context, edit_window).await,
+    })
+}
+
+/// Packs as many whole sentences into each piece as fit in `limit`. A sentence longer than that
+/// is left as it is, for the length check to reject.
+fn split(segment: &str, limit: usize) -> Vec<String> {
+    let segment = segment.trim();
+    if weighted_length(segment) <= limit {
+        return if segment.is_empty() {
+            Vec::new()
+        } else {
+            vec![segment.to_string()]
+        };
+    }
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for sentence in text::sentences(segment) {
+        let joined = format!("{}{}", current, sentence);
+        if !current.trim().is_empty() && weighted_length(joined.trim()) > limit {
+            pieces.push(current.trim().to_string());
+            current = sentence.to_string();
+        } else {
+            current = joined;
+        }
+    }
+    if !current.trim().is_empty() {
+        pieces.push(current.trim().to_string());
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::{Account, Tweet};
+    use crate::media_store::MockMediaStore;
+    use crate::repositories::{MockAccounts, MockTweets};
+    use crate::request::UserContext;
+    use crate::services::TweetError;
+    use crate::tweet_length::weighted_length;
+
+    fn account(id: i32) -> Account {
+        let mut account = Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("password{}", id),
+            format!("display_name{}", id),
+        );
+        account.email_verified = true;
+        account
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(super::split("  short  ", 280), vec!["short".to_string()]);
+        assert!(super::split(" \n ", 280).is_empty());
+
+        // 100 full-width characters weigh 200, so two don't fit together.
+        let sentence = format!("{}。", "あ".repeat(99));
+        let segment = format!("{} {}\n{}", sentence, sentence, sentence);
+        let pieces = super::split(&segment, 280);
+        assert_eq!(pieces, vec![sentence.clone(), sentence.clone(), sentence]);
+
+        let segment = format!("{}. ", "word ".repeat(20).trim()).repeat(4);
+        let pieces = super::split(&segment, 280);
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces.iter().all(|x| weighted_length(x) <= 280));
+        assert_eq!(pieces.join(" "), segment.trim());
+    }
+
+    #[tokio::test]
+    async fn test_create_thread() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store_thread()
+            .withf(|e: &[Tweet]| {
+                e.len() == 3
+                    && e.iter()
+                        .all(|x| x.content_warning.as_deref() == Some("ネタバレ"))
+                    && e[0].message == "first #rust 1/3"
+                    && e[0].tags == vec!["rust".to_string()]
+                    && e[2].message.ends_with("。 3/3")
+            })
+            .once()
+            .return_const(());
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let user_context = UserContext { user_id: 1 };
+        let long = format!("{}。", "あ".repeat(99)).repeat(2);
+        let segments = vec!["first #rust".to_string(), " ".to_string(), long];
+        let result = super::create_thread(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            &segments,
+            true,
+            "ネタバレ",
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_create_thread_rolls_back() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_store_thread().never();
+        tweets.expect_store().never();
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let user_context = UserContext { user_id: 1 };
+        // A single sentence over the limit can't be split.
+        let segments = vec!["first".to_string(), "あ".repeat(141)];
+        let result = super::create_thread(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            &segments,
+            false,
+            "",
+        )
+        .await;
+        assert_eq!(result, Err(TweetError::TooLong));
+
+        let segments = vec!["a".to_string(); 26];
+        let result = super::create_thread(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            &segments,
+            false,
+            "",
+        )
+        .await;
+        assert_eq!(result, Err(TweetError::ThreadTooLong));
+
+        let result = super::create_thread(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            &[String::new()],
+            false,
+            "",
+        )
+        .await;
+        assert_eq!(result, Err(TweetError::EmptyThread));
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::{Duration, Utc};
+
+use crate::entities::Tweet;
+use crate::media_store::MediaStore;
+use crate::repositories::Tweets;
+use crate::request::UserContext;
+use crate::views::{DeletedTweet, Trash};
+
+/// How long deleted tweets can be restored before they are purged.
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+pub async fn list_trash(repo: &impl Tweets, user_context: &UserContext) -> Trash {
+    let since = Utc::now() - retention();
+    let tweets = repo.list_deleted_by(user_context.user_id, since).await;
+    Trash {
+        tweets: tweets.into_iter().map(|x| x.into()).collect(),
+    }
+}
+
+/// Returns whether the tweet was restored.
+pub async fn restore_tweet(repo: &impl Tweets, user_context: &UserContext, id: i32) -> bool {
+    let tweet = repo
+        .find_deleted(id)
+        .await
+        .filter(|x| x.is_restorable_by(user_context.user_id, Utc::now(), retention()));
+    match tweet {
+        Some(mut tweet) => {
+            tweet.restore();
+            repo.store(&tweet).await;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Deletes the tweets past the retention period for good, along with the files of their images.
+pub async fn purge_deleted_tweets(repo: &impl Tweets, media_store: &impl MediaStore) {
+    for media in repo.purge_deleted(Utc::now() - retention()).await {
+        media_store.delete(&media.key).await;
+        media_store.delete(&media.thumbnail_key).await;
+    }
+}
+
+fn retention() -> Duration {
+    Duration::days(TRASH_RETENTION_DAYS)
+}
+
+impl From<Tweet> for DeletedTweet {
+    fn from(e: Tweet) -> Self {
+        let deleted_at = e.deleted_at.unwrap_or_else(Utc::now);
+        DeletedTweet {
+            id: e.id().unwrap_or(-1),
+            message: e.message,
+            image_count: e.media.len(),
+            deleted_at: deleted_at.format("%Y/%m/%d %H:%M").to_string(),
+            restorable_until: (deleted_at + retention())
+                .format("%Y/%m/%d %H:%M")
+                .to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::entities::{Media, Tweet};
+    use crate::media_store::MockMediaStore;
+    use crate::repositories::MockTweets;
+    use crate::request::UserContext;
+
+    fn deleted_tweet(id: i32, account_id: i32, days_ago: i64) -> Tweet {
+        let mut tweet = Tweet::new(
+            id,
+            format!("message{}", id),
+            Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            account_id,
+        );
+        tweet.delete(Utc::now() - Duration::days(days_ago));
+        tweet
+    }
+
+    #[tokio::test]
+    async fn test_list_trash() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_list_deleted_by()
+            .withf(|account_id, since| *account_id == 1 && *since < Utc::now() - Duration::days(29))
+            .returning(|_, _| vec![deleted_tweet(1, 1, 1)]);
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::list_trash(&tweets, &user_context).await;
+        assert_eq!(result.tweets.len(), 1);
+        assert_eq!(result.tweets[0].id, 1);
+        assert_eq!(result.tweets[0].message, "message1");
+    }
+
+    #[tokio::test]
+    async fn test_restore_tweet() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_find_deleted()
+            .returning(|id| Some(deleted_tweet(id, 1, 1)));
+        tweets
+            .expect_store()
+            .withf(|e| e.id() == Some(1) && e.deleted_at.is_none())
+            .once()
+            .return_const(1);
+
+        let user_context = UserContext { user_id: 1 };
+        assert!(super::restore_tweet(&tweets, &user_context, 1).await);
+    }
+
+    #[tokio::test]
+    async fn test_restore_tweet_not_restorable() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_find_deleted().returning(|id| match id {
+            1 => Some(deleted_tweet(1, 2, 1)),
+            _ => Some(deleted_tweet(id, 1, 31)),
+        });
+        tweets.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        assert!(!super::restore_tweet(&tweets, &user_context, 1).await);
+        assert!(!super::restore_tweet(&tweets, &user_context, 2).await);
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted_tweets() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_purge_deleted()
+            .withf(|before| *before < Utc::now() - Duration::days(29))
+            .returning(|_| {
+                vec![Media {
+                    key: "abc.jpg".to_string(),
+                    thumbnail_key: "abc_thumb.jpg".to_string(),
+                    content_type: "image/jpeg".to_string(),
+                    alt_text: String::new(),
+                    width: 640,
+                    height: 480,
+                }]
+            });
+
+        let mut media_store = MockMediaStore::new();
+        media_store
+            .expect_delete()
+            .withf(|key| key == "abc.jpg" || key == "abc_thumb.jpg")
+            .times(2)
+            .return_const(());
+
+        super::purge_deleted_tweets(&tweets, &media_store).await;
+    }
+}
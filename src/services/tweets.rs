@@ -1,43 +1,370 @@
+use chrono::{DateTime, Duration, Utc};
 use std::collections::HashSet;
 
-use crate::entities::Tweet;
+use crate::entities::{Account, Media, Mention, Tweet};
+use crate::images::{self, ImageError};
+use crate::media_store::MediaStore;
 use crate::repositories::{Accounts, Tweets};
 use crate::request::UserContext;
-use crate::views::Home;
+use crate::text;
+use crate::token;
+use crate::tweet_length::{self, MAX_LENGTH};
+use crate::views::{self, Home, Mentions, Revision, Tag, TweetEdit, TweetHistory, TweetLength};
 
-pub async fn list_tweets(repo: &impl Tweets, account_repo: &impl Accounts) -> Home {
+const MAX_IMAGES: usize = 4;
+const MAX_ALT_TEXT_LENGTH: usize = 1000;
+const MAX_CONTENT_WARNING_LENGTH: usize = 100;
+
+pub async fn list_tweets(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    edit_window: Duration,
+) -> Home {
     let tweets = repo.list().await;
-    let posted_account_ids = tweets.iter().map(|x| x.posted_by).collect::<HashSet<i32>>();
-    let accounts = account_repo.find(posted_account_ids).await;
-    let tweets = tweets
+    Home {
+        tweets: to_views(tweets, account_repo, user_context, edit_window).await,
+        error: String::new(),
+        notice: String::new(),
+        draft: None,
+        pending: None,
+    }
+}
+
+pub async fn list_mentions(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    edit_window: Duration,
+) -> Mentions {
+    let tweets = repo.list_mentioning(user_context.user_id).await;
+    Mentions {
+        tweets: to_views(tweets, account_repo, user_context, edit_window).await,
+    }
+}
+
+pub async fn list_tagged(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    edit_window: Duration,
+    tag: &str,
+) -> Tag {
+    let tag = text::normalize(tag);
+    let tweets = repo.list_tagged(&tag).await;
+    Tag {
+        tweets: to_views(tweets, account_repo, user_context, edit_window).await,
+        tag,
+    }
+}
+
+/// Looks up the posters and mentioned accounts of the tweets in one query.
+/// The viewer gets to edit their own tweets posted within `edit_window`.
+pub(super) async fn to_views(
+    tweets: Vec<Tweet>,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    edit_window: Duration,
+) -> Vec<views::Tweet> {
+    let account_ids = tweets
+        .iter()
+        .flat_map(|x| std::iter::once(x.posted_by).chain(x.mentions.iter().map(|m| m.account_id)))
+        .chain(std::iter::once(user_context.user_id))
+        .collect::<HashSet<i32>>();
+    let accounts = account_repo.find(account_ids).await;
+    let expanded = accounts
+        .get(&user_context.user_id)
+        .map(|x| x.always_expand_content_warnings)
+        .unwrap_or(false);
+    let now = Utc::now();
+    tweets
         .into_iter()
         .map(|x| {
             let account = accounts.get(&x.posted_by).unwrap();
-            (x, account).into()
+            let editable = x.is_editable_by(user_context.user_id, now, edit_window);
+            let own = x.posted_by == user_context.user_id;
+            let mut tweet: views::Tweet = (x, account, &accounts).into();
+            tweet.editable = editable;
+            tweet.own = own;
+            tweet.expanded = expanded;
+            tweet
         })
-        .collect();
-    Home { tweets }
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TweetError {
+    Unverified,
+    TooLong,
+    TooManyImages,
+    ImageTooLarge,
+    UnsupportedImage,
+    AltTextTooLong,
+    /// Not the author's, or past the edit window.
+    NotEditable,
+    /// The time to publish at is missing, malformed or not in the future.
+    InvalidSchedule,
+    /// A thread with nothing in it.
+    EmptyThread,
+    /// More segments than a thread can have, after splitting.
+    ThreadTooLong,
+    ContentWarningTooLong,
+}
+
+/// What the composer sends for a new tweet.
+#[derive(Default)]
+pub struct NewTweet {
+    pub message: String,
+    /// Empty for none.
+    pub content_warning: String,
+    pub images: Vec<ImageUpload>,
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+pub struct ImageUpload {
+    pub bytes: Vec<u8>,
+    /// Optional, but the composer asks for it.
+    pub alt_text: String,
+}
+
+/// What became of a new tweet.
+#[derive(Debug, PartialEq)]
+pub enum Posted {
+    Published,
+    /// Held back for the user's undo delay, and can still be taken back until the time given.
+    Pending(i32, DateTime<Utc>),
+    Scheduled,
 }
 
-pub async fn create_tweet(repo: &impl Tweets, user_context: &UserContext, message: &str) {
-    let new_tweet = Tweet::create(message, user_context.user_id);
-    repo.store(&new_tweet).await;
+/// Posts the tweet, or holds it back for a few seconds if the user has set an undo delay.
+/// The scheduled tweet publisher makes it visible once the delay is over.
+pub async fn create_tweet(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    media_store: &impl MediaStore,
+    user_context: &UserContext,
+    new_tweet: NewTweet,
+) -> Result<Posted, TweetError> {
+    let account = posting_account(account_repo, user_context).await?;
+    let publish_at = new_tweet.publish_at;
+    let mut new_tweet = build_tweet(&account, account_repo, media_store, new_tweet).await?;
+    if publish_at.is_none() && account.undo_send_seconds > 0 {
+        new_tweet.publish_at =
+            Some(Utc::now() + Duration::seconds(account.undo_send_seconds.into()));
+        new_tweet.pending = true;
+    }
+    let id = repo.store(&new_tweet).await;
+    Ok(match (publish_at, new_tweet.publish_at) {
+        (Some(_), _) => Posted::Scheduled,
+        (None, Some(pending_until)) => Posted::Pending(id, pending_until),
+        (None, None) => Posted::Published,
+    })
+}
+
+/// The user's account, if it may post.
+pub(super) async fn posting_account(
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+) -> Result<Account, TweetError> {
+    account_repo
+        .find_by_id(user_context.user_id)
+        .await
+        .filter(|x| x.can_post())
+        .ok_or(TweetError::Unverified)
+}
+
+/// Checks a new tweet and prepares it for storing, writing the files of its images.
+pub(super) async fn build_tweet(
+    account: &Account,
+    account_repo: &impl Accounts,
+    media_store: &impl MediaStore,
+    new_tweet: NewTweet,
+) -> Result<Tweet, TweetError> {
+    let NewTweet {
+        message,
+        content_warning,
+        images,
+        publish_at,
+    } = new_tweet;
+    if tweet_length::weighted_length(&message) > MAX_LENGTH {
+        return Err(TweetError::TooLong);
+    }
+    let content_warning = content_warning.trim();
+    if content_warning.chars().count() > MAX_CONTENT_WARNING_LENGTH {
+        return Err(TweetError::ContentWarningTooLong);
+    }
+    if images.len() > MAX_IMAGES {
+        return Err(TweetError::TooManyImages);
+    }
+    if publish_at.map(|x| x <= Utc::now()).unwrap_or(false) {
+        return Err(TweetError::InvalidSchedule);
+    }
+
+    // Everything is validated before the first file is written, so a bad image leaves nothing behind.
+    let mut processed = Vec::new();
+    for image in images {
+        if image.alt_text.chars().count() > MAX_ALT_TEXT_LENGTH {
+            return Err(TweetError::AltTextTooLong);
+        }
+        let result = images::process(&image.bytes).map_err(|e| match e {
+            ImageError::TooLarge => TweetError::ImageTooLarge,
+            ImageError::Unsupported => TweetError::UnsupportedImage,
+        })?;
+        processed.push((result, image.alt_text.trim().to_string()));
+    }
+
+    let mut new_tweet = Tweet::create(&message, account.id().unwrap());
+    new_tweet.publish_at = publish_at;
+    if !content_warning.is_empty() {
+        new_tweet.content_warning = Some(content_warning.to_string());
+    }
+    for (image, alt_text) in processed {
+        let name = token::generate();
+        let media = Media {
+            key: format!("{}.{}", name, image.extension),
+            thumbnail_key: format!("{}_thumb.{}", name, image.extension),
+            content_type: image.content_type.to_string(),
+            alt_text,
+            width: image.width,
+            height: image.height,
+        };
+        media_store.put(&media.key, image.bytes).await;
+        media_store.put(&media.thumbnail_key, image.thumbnail).await;
+        new_tweet.media.push(media);
+    }
+    extract_details(&mut new_tweet, account_repo).await;
+    Ok(new_tweet)
+}
+
+/// Sets the hashtags, links and mentions from the message.
+pub(super) async fn extract_details(tweet: &mut Tweet, account_repo: &impl Accounts) {
+    tweet.tags = text::hashtags(&tweet.message);
+    // The previews are fetched in the background, so posting never waits on another site.
+    tweet.links = text::urls(&tweet.message);
+    tweet.mentions.clear();
+    for handle in text::mentions(&tweet.message) {
+        if let Some(account) = account_repo.find_by_handle(&handle).await {
+            if !account.is_deleted() {
+                tweet.mentions.push(Mention {
+                    account_id: account.id().unwrap(),
+                    handle,
+                });
+            }
+        }
+    }
 }
 
-pub async fn delete_tweet(repo: &impl Tweets, id: i32) {
-    let tweet = repo.find(id).await;
+/// The edit form, if the tweet is still editable by the user.
+pub async fn edit_form(
+    repo: &impl Tweets,
+    user_context: &UserContext,
+    edit_window: Duration,
+    id: i32,
+    error: &str,
+) -> Option<TweetEdit> {
+    let tweet = repo.find(id).await?;
+    if !tweet.is_editable_by(user_context.user_id, Utc::now(), edit_window) {
+        return None;
+    }
+    Some(TweetEdit {
+        id,
+        message: tweet.message,
+        error: error.to_string(),
+    })
+}
+
+/// Edits follow the same rules as new tweets. Images stay as they are.
+pub async fn edit_tweet(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    edit_window: Duration,
+    id: i32,
+    message: &str,
+) -> Result<(), TweetError> {
+    let now = Utc::now();
+    let mut tweet = repo
+        .find(id)
+        .await
+        .filter(|x| x.is_editable_by(user_context.user_id, now, edit_window))
+        .ok_or(TweetError::NotEditable)?;
+    if tweet_length::weighted_length(message) > MAX_LENGTH {
+        return Err(TweetError::TooLong);
+    }
+    if tweet.message == message {
+        return Ok(());
+    }
+    tweet.edit(message, now);
+    extract_details(&mut tweet, account_repo).await;
+    repo.store(&tweet).await;
+    Ok(())
+}
+
+pub async fn tweet_history(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    edit_window: Duration,
+    id: i32,
+) -> Option<TweetHistory> {
+    let tweet = repo.find(id).await?;
+    let revisions = repo.list_revisions(id).await;
+    let tweet = to_views(vec![tweet], account_repo, user_context, edit_window)
+        .await
+        .pop()?;
+    Some(TweetHistory {
+        tweet,
+        revisions: revisions
+            .into_iter()
+            .map(|x| Revision {
+                message: x.message,
+                written_at: x.written_at.format("%Y/%m/%d %H:%M").to_string(),
+            })
+            .collect(),
+    })
+}
+
+/// Counts the message with the same rules `create_tweet` applies, for the composer.
+pub fn measure_tweet(message: &str) -> TweetLength {
+    let length = tweet_length::weighted_length(message);
+    TweetLength {
+        length,
+        remaining: MAX_LENGTH as i64 - length as i64,
+    }
+}
+
+/// Moves the tweet to the trash. Its images stay until it is purged, so it can be restored.
+/// A pinned tweet is unpinned, and stays so if it is restored.
+/// Moves one of the user's own tweets to the trash.
+pub async fn delete_tweet(
+    repo: &impl Tweets,
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    id: i32,
+) {
+    let tweet = repo
+        .find(id)
+        .await
+        .filter(|x| x.posted_by == user_context.user_id);
     if let Some(mut tweet) = tweet {
-        tweet.delete();
+        tweet.delete(Utc::now());
         repo.store(&tweet).await;
+
+        let account = account_repo.find_by_id(tweet.posted_by).await;
+        if let Some(mut account) = account.filter(|x| x.pinned_tweet_id == Some(id)) {
+            account.pinned_tweet_id = None;
+            account_repo.store(&account).await;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::{TimeZone, Utc};
+    use chrono::{Duration, TimeZone, Utc};
     use std::collections::HashMap;
 
-    use crate::entities::{Account, Tweet};
+    use crate::entities::{Account, Mention, Tweet, TweetRevision};
+    use crate::media_store::MockMediaStore;
     use crate::repositories::{MockAccounts, MockTweets};
     use crate::request::UserContext;
 
@@ -51,12 +378,15 @@ mod tests {
     }
 
     fn account(id: i32) -> Account {
-        Account::new(
+        let mut account = Account::new(
             id,
             format!("{}@example.com", id),
             format!("password{}", id),
             format!("display_name{}", id),
-        )
+        );
+        account.handle = format!("handle{}", id);
+        account.email_verified = true;
+        account
     }
 
     #[tokio::test]
@@ -74,14 +404,53 @@ mod tests {
             result
         });
 
-        let result = super::list_tweets(&tweets, &accounts).await;
+        let result = super::list_tweets(
+            &tweets,
+            &accounts,
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+        )
+        .await;
         assert_eq!(result.tweets.len(), 2);
         let result0 = result.tweets.get(0).unwrap();
-        assert_eq!(result0.message, "message2");
+        assert_eq!(result0.blocks[0].lines[0][0].text, "message2");
         assert_eq!(result0.posted_at, "2020/01/01 00:00");
         assert_eq!(result0.name, "display_name2");
     }
 
+    #[tokio::test]
+    async fn test_list_tweets_expanded() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_list().returning(|| {
+            let mut tweet = tweet(1, 2);
+            tweet.content_warning = Some("ネタバレ".to_string());
+            vec![tweet]
+        });
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find().returning(|ids| {
+            ids.into_iter()
+                .map(|id| {
+                    let mut account = account(id);
+                    account.always_expand_content_warnings = id == 1;
+                    (id, account)
+                })
+                .collect()
+        });
+
+        let result = super::list_tweets(
+            &tweets,
+            &accounts,
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+        )
+        .await;
+        let result0 = result.tweets.get(0).unwrap();
+        assert_eq!(result0.content_warning.as_deref(), Some("ネタバレ"));
+        assert!(result0.expanded);
+        assert!(!result0.own);
+    }
+
     #[tokio::test]
     async fn test_list_tweets_empty() {
         let mut tweets = MockTweets::new();
@@ -90,7 +459,13 @@ mod tests {
         let mut accounts = MockAccounts::new();
         accounts.expect_find().returning(|_| HashMap::new());
 
-        let result = super::list_tweets(&tweets, &accounts).await;
+        let result = super::list_tweets(
+            &tweets,
+            &accounts,
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+        )
+        .await;
         assert_eq!(result.tweets.is_empty(), true);
     }
 
@@ -103,10 +478,517 @@ mod tests {
             .expect_store()
             .withf(|e| e.message == tweet(1, 1).message && e.posted_by == 1)
             .once()
-            .return_const(());
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
 
         let tweet = tweet(1, 1);
-        super::create_tweet(&tweets, &user_context, &tweet.message).await;
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: tweet.message.clone(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(super::Posted::Published));
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_held_for_undo() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store()
+            .withf(|e| {
+                let publish_at = e.publish_at.unwrap();
+                e.pending
+                    && publish_at > Utc::now() + Duration::seconds(5)
+                    && publish_at <= Utc::now() + Duration::seconds(10)
+            })
+            .once()
+            .return_const(7);
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|id| {
+            let mut account = account(id);
+            account.undo_send_seconds = 10;
+            Some(account)
+        });
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "message".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Ok(super::Posted::Pending(7, _))));
+    }
+
+    #[tokio::test]
+    async fn test_create_scheduled_tweet() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store()
+            .withf(|e| e.is_scheduled())
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let media_store = MockMediaStore::new();
+        let publish_at = Utc::now() + Duration::hours(1);
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &media_store,
+            &user_context,
+            super::NewTweet {
+                message: "message".to_string(),
+                publish_at: Some(publish_at),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(super::Posted::Scheduled));
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &media_store,
+            &user_context,
+            super::NewTweet {
+                message: "message".to_string(),
+                publish_at: Some(Utc::now() - Duration::minutes(1)),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Err(super::TweetError::InvalidSchedule));
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_too_long() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        // 141 full-width characters weigh 282.
+        let result = super::create_tweet(
+            &MockTweets::new(),
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "あ".repeat(141),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Err(super::TweetError::TooLong));
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_with_content_warning() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store()
+            .withf(|e| e.content_warning.as_deref() == Some("ネタバレ"))
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "message".to_string(),
+                content_warning: " ネタバレ ".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(super::Posted::Published));
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "message".to_string(),
+                content_warning: "あ".repeat(101),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Err(super::TweetError::ContentWarningTooLong));
+    }
+
+    #[test]
+    fn test_measure_tweet() {
+        let result = super::measure_tweet(&"あ".repeat(140));
+        assert_eq!(result.length, 280);
+        assert_eq!(result.remaining, 0);
+        let result = super::measure_tweet(&format!("{} https://example.com", "a".repeat(260)));
+        assert_eq!(result.remaining, -4);
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_with_mentions() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store()
+            .withf(|e| {
+                e.mentions
+                    == vec![Mention {
+                        account_id: 2,
+                        handle: "handle2".to_string(),
+                    }]
+            })
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_find_by_handle()
+            .returning(|handle| match handle {
+                "handle2" => Some(account(2)),
+                _ => None,
+            });
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "@Handle2 @nobody hello".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(super::Posted::Published));
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_with_hashtags() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store()
+            .withf(|e| e.tags == vec!["rust".to_string(), "東京".to_string()])
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "#Rust #東京 #ＲＵＳＴ".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(super::Posted::Published));
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_with_links() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_store()
+            .withf(|e| {
+                e.links
+                    == vec![
+                        "https://example.com/a#b".to_string(),
+                        "http://example.org".to_string(),
+                    ]
+                    && e.tags.is_empty()
+            })
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "https://example.com/a#b と http://example.org。".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(super::Posted::Published));
+    }
+
+    #[tokio::test]
+    async fn test_edit_tweet() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets.expect_find().returning(|id| {
+            let mut tweet = tweet(id, 1);
+            tweet.posted_at = Utc::now() - Duration::minutes(10);
+            Some(tweet)
+        });
+        tweets
+            .expect_store()
+            .withf(|e| {
+                e.id() == Some(1)
+                    && e.message == "#Rust を @handle2 と"
+                    && e.edited_at.is_some()
+                    && e.tags == vec!["rust".to_string()]
+                    && e.mentions.len() == 1
+            })
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_handle()
+            .returning(|_| Some(account(2)));
+
+        let result = super::edit_tweet(
+            &tweets,
+            &accounts,
+            &user_context,
+            Duration::minutes(30),
+            1,
+            "#Rust を @handle2 と",
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_edit_tweet_not_editable() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_find().returning(|id| match id {
+            // Someone else's tweet, posted just now.
+            1 => {
+                let mut tweet = tweet(id, 2);
+                tweet.posted_at = Utc::now();
+                Some(tweet)
+            }
+            // An old tweet of the user's.
+            _ => Some(tweet(id, 1)),
+        });
+        tweets.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let accounts = MockAccounts::new();
+        for id in [1, 2] {
+            let result = super::edit_tweet(
+                &tweets,
+                &accounts,
+                &user_context,
+                Duration::minutes(30),
+                id,
+                "edited",
+            )
+            .await;
+            assert_eq!(result, Err(super::TweetError::NotEditable));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tweet_history() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_find().returning(|id| {
+            let mut tweet = tweet(id, 1);
+            tweet.edited_at = Some(Utc.ymd(2020, 1, 2).and_hms(0, 0, 0));
+            Some(tweet)
+        });
+        tweets.expect_list_revisions().returning(|_| {
+            vec![TweetRevision {
+                message: "before".to_string(),
+                written_at: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            }]
+        });
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find()
+            .returning(|ids| ids.into_iter().map(|id| (id, account(id))).collect());
+
+        let user_context = UserContext { user_id: 1 };
+        let result =
+            super::tweet_history(&tweets, &accounts, &user_context, Duration::minutes(30), 1)
+                .await
+                .unwrap();
+        assert!(result.tweet.edited);
+        assert!(!result.tweet.editable);
+        assert_eq!(result.revisions[0].message, "before");
+        assert_eq!(result.revisions[0].written_at, "2020/01/01 00:00");
+    }
+
+    #[tokio::test]
+    async fn test_list_tagged() {
+        let mut tweets = MockTweets::new();
+        tweets
+            .expect_list_tagged()
+            .withf(|tag| tag == "rust")
+            .returning(|_| {
+                let mut tweet = tweet(1, 1);
+                tweet.message = "#Rust".to_string();
+                vec![tweet]
+            });
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find()
+            .returning(|ids| ids.into_iter().map(|id| (id, account(id))).collect());
+
+        let result = super::list_tagged(
+            &tweets,
+            &accounts,
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+            "ＲＵＳＴ",
+        )
+        .await;
+        assert_eq!(result.tag, "rust");
+        assert_eq!(
+            result.tweets[0].blocks[0].lines[0][0].href.as_deref(),
+            Some("/tags/rust")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_mentions() {
+        let user_context = UserContext { user_id: 2 };
+
+        let mut tweets = MockTweets::new();
+        tweets.expect_list_mentioning().returning(|_| {
+            let mut tweet = tweet(1, 1);
+            tweet.message = "@handle2 @nobody".to_string();
+            tweet.mentions = vec![Mention {
+                account_id: 2,
+                handle: "handle2".to_string(),
+            }];
+            vec![tweet]
+        });
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find()
+            .returning(|ids| ids.into_iter().map(|id| (id, account(id))).collect());
+
+        let result =
+            super::list_mentions(&tweets, &accounts, &user_context, Duration::minutes(30)).await;
+        let segments = &result.tweets[0].blocks[0].lines[0];
+        assert_eq!(segments[0].href.as_deref(), Some("/@handle2"));
+        assert_eq!(segments[1].text, " @nobody");
+        assert_eq!(segments[1].href, None);
+    }
+
+    #[tokio::test]
+    async fn test_list_tweets_rich_text() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_list().returning(|| {
+            let mut tweet = tweet(1, 1);
+            tweet.message = "見て https://example.com/\n<b>太字</b>\n\n> *引用*".to_string();
+            vec![tweet]
+        });
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find()
+            .returning(|ids| ids.into_iter().map(|id| (id, account(id))).collect());
+
+        let result = super::list_tweets(
+            &tweets,
+            &accounts,
+            &UserContext { user_id: 1 },
+            Duration::minutes(30),
+        )
+        .await;
+        let blocks = &result.tweets[0].blocks;
+        assert_eq!(blocks.len(), 2);
+        let url = &blocks[0].lines[0][1];
+        assert_eq!(url.href.as_deref(), Some("https://example.com/"));
+        assert!(url.external);
+        assert_eq!(blocks[0].lines[1][0].text, "<b>太字</b>");
+        assert!(blocks[1].quote);
+        assert!(blocks[1].lines[0][0].style.emphasis);
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_unverified() {
+        let user_context = UserContext { user_id: 1 };
+
+        let mut tweets = MockTweets::new();
+        tweets.expect_store().never();
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|id| {
+            let mut account = account(id);
+            account.email_verified = false;
+            Some(account)
+        });
+
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &MockMediaStore::new(),
+            &user_context,
+            super::NewTweet {
+                message: "message1".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Err(super::TweetError::Unverified));
     }
 
     #[tokio::test]
@@ -115,11 +997,118 @@ mod tests {
         tweets.expect_find().returning(|_| Some(tweet(1, 1)));
         tweets
             .expect_store()
-            .withf(|e| e.id() == Some(1) && e.is_deleted())
+            .withf(|e| e.id() == Some(1) && e.deleted_at.is_some())
+            .once()
+            .return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_store().never();
+
+        super::delete_tweet(&tweets, &accounts, &UserContext { user_id: 1 }, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_tweet_of_another_account() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_find().returning(|_| Some(tweet(1, 2)));
+        tweets.expect_store().never();
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_store().never();
+
+        super::delete_tweet(&tweets, &accounts, &UserContext { user_id: 1 }, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_pinned_tweet() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_find().returning(|_| Some(tweet(1, 1)));
+        tweets.expect_store().return_const(1);
+
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|id| {
+            let mut account = account(id);
+            account.pinned_tweet_id = Some(1);
+            Some(account)
+        });
+        accounts
+            .expect_store()
+            .withf(|e| e.id() == Some(1) && e.pinned_tweet_id.is_none())
             .once()
             .return_const(());
 
-        super::delete_tweet(&tweets, 1).await;
+        super::delete_tweet(&tweets, &accounts, &UserContext { user_id: 1 }, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_too_many_images() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_store().never();
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let mut media_store = MockMediaStore::new();
+        media_store.expect_put().never();
+
+        let images = (0..5)
+            .map(|_| super::ImageUpload {
+                bytes: vec![],
+                alt_text: String::new(),
+            })
+            .collect();
+        let user_context = UserContext { user_id: 1 };
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &media_store,
+            &user_context,
+            super::NewTweet {
+                message: "message1".to_string(),
+                images,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Err(super::TweetError::TooManyImages));
+    }
+
+    #[tokio::test]
+    async fn test_create_tweet_unsupported_image() {
+        let mut tweets = MockTweets::new();
+        tweets.expect_store().never();
+
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+
+        let mut media_store = MockMediaStore::new();
+        media_store.expect_put().never();
+
+        let images = vec![super::ImageUpload {
+            bytes: b"<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>".to_vec(),
+            alt_text: "logo".to_string(),
+        }];
+        let user_context = UserContext { user_id: 1 };
+        let result = super::create_tweet(
+            &tweets,
+            &accounts,
+            &media_store,
+            &user_context,
+            super::NewTweet {
+                message: "message1".to_string(),
+                images,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(result, Err(super::TweetError::UnsupportedImage));
     }
 
     #[tokio::test]
@@ -128,6 +1117,12 @@ mod tests {
         tweets.expect_find().returning(|_| None);
         tweets.expect_store().never();
 
-        super::delete_tweet(&tweets, 1).await;
+        super::delete_tweet(
+            &tweets,
+            &MockAccounts::new(),
+            &UserContext { user_id: 1 },
+            1,
+        )
+        .await;
     }
 }
@@ -0,0 +1,438 @@
+use chrono::Utc;
+
+use crate::entities::{Account, LoginAttempt};
+use crate::repositories::{Accounts, LoginAttempts, RecoveryCodes};
+use crate::request::{PendingUserContext, UserContext};
+use crate::services::accounts::{
+    cancel_scheduled_deletion, destroy_session, issue_verified_session,
+};
+use crate::services::{SessionError, SessionToken};
+use crate::token;
+use crate::totp;
+use crate::views::TwoFactorSettings;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Shows the secret to enroll while two-factor authentication is off or being enrolled again.
+/// The secret is kept on the account, so reloading the page shows the same one.
+pub async fn two_factor_settings(
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+) -> TwoFactorSettings {
+    let mut account = account_repo.find_by_id(user_context.user_id).await.unwrap();
+    let enabled = account.two_factor_enabled();
+    if enabled && account.totp_pending_secret.is_none() {
+        return TwoFactorSettings {
+            enabled,
+            reenrolling: false,
+            secret: String::new(),
+            qr_code_svg: String::new(),
+            error: false,
+        };
+    }
+
+    let secret = match &account.totp_pending_secret {
+        Some(secret) => secret.clone(),
+        None => {
+            let secret = totp::generate_secret();
+            account.totp_pending_secret = Some(secret.clone());
+            account_repo.store(&account).await;
+            secret
+        }
+    };
+    let uri = totp::provisioning_uri(&secret, &account.email);
+    TwoFactorSettings {
+        enabled,
+        reenrolling: enabled,
+        qr_code_svg: totp::qr_code_svg(&uri),
+        secret,
+        error: false,
+    }
+}
+
+/// Confirms the pending secret with a code from the authenticator. Returns the recovery codes
+/// to show to the user once, or `None` if the password or code is wrong.
+pub async fn enable_two_factor(
+    account_repo: &impl Accounts,
+    recovery_repo: &impl RecoveryCodes,
+    user_context: &UserContext,
+    code: &str,
+    password: &str,
+) -> Option<Vec<String>> {
+    let mut account = account_repo.find_by_id(user_context.user_id).await?;
+    if !account.matches_password(password) {
+        return None;
+    }
+
+    if !account.confirm_two_factor(code, Utc::now()) {
+        return None;
+    }
+    account_repo.store(&account).await;
+
+    Some(replace_recovery_codes(recovery_repo, &account).await)
+}
+
+pub async fn regenerate_recovery_codes(
+    account_repo: &impl Accounts,
+    recovery_repo: &impl RecoveryCodes,
+    user_context: &UserContext,
+    password: &str,
+) -> Option<Vec<String>> {
+    let account = account_repo.find_by_id(user_context.user_id).await?;
+    if !account.two_factor_enabled() || !account.matches_password(password) {
+        return None;
+    }
+
+    Some(replace_recovery_codes(recovery_repo, &account).await)
+}
+
+/// Starts enrolling another authenticator. The current one keeps working until the new one is
+/// confirmed.
+pub async fn reenroll_two_factor(
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+    password: &str,
+) -> bool {
+    let mut account = match account_repo.find_by_id(user_context.user_id).await {
+        Some(account) if account.two_factor_enabled() && account.matches_password(password) => {
+            account
+        }
+        _ => return false,
+    };
+
+    account.totp_pending_secret = Some(totp::generate_secret());
+    account_repo.store(&account).await;
+    true
+}
+
+pub async fn cancel_two_factor_reenrollment(
+    account_repo: &impl Accounts,
+    user_context: &UserContext,
+) {
+    let mut account = account_repo.find_by_id(user_context.user_id).await.unwrap();
+    if account.two_factor_enabled() && account.totp_pending_secret.is_some() {
+        account.totp_pending_secret = None;
+        account_repo.store(&account).await;
+    }
+}
+
+pub async fn disable_two_factor(
+    account_repo: &impl Accounts,
+    recovery_repo: &impl RecoveryCodes,
+    user_context: &UserContext,
+    password: &str,
+) -> bool {
+    let mut account = match account_repo.find_by_id(user_context.user_id).await {
+        Some(account) if account.matches_password(password) => account,
+        _ => return false,
+    };
+
+    account.disable_two_factor();
+    account_repo.store(&account).await;
+    recovery_repo.replace(account.id().unwrap(), vec![]).await;
+    true
+}
+
+/// The second login step. Accepts either a current TOTP code or an unused recovery code.
+pub async fn complete_two_factor(
+    account_repo: &impl Accounts,
+    recovery_repo: &impl RecoveryCodes,
+    attempt_repo: &impl LoginAttempts,
+    pending: &PendingUserContext,
+    code: &str,
+) -> Result<SessionToken, SessionError> {
+    let now = Utc::now();
+    let attempt_key = LoginAttempt::two_factor_key(pending.user_id);
+    if let Some(attempt) = attempt_repo.find_by(&attempt_key).await {
+        if attempt.is_blocked(now) {
+            return Err(SessionError::TooManyAttempts);
+        }
+    }
+
+    let mut account = account_repo
+        .find_by_id(pending.user_id)
+        .await
+        .ok_or(SessionError::Invalid)?;
+    let verified = if account.verify_totp(code, now) {
+        account_repo.store(&account).await;
+        true
+    } else {
+        recovery_repo
+            .consume(pending.user_id, &recovery_code_hash(code))
+            .await
+    };
+    if !verified {
+        attempt_repo.record_failure(&attempt_key, now).await;
+        return Err(SessionError::Invalid);
+    }
+    attempt_repo.reset(&attempt_key).await;
+    cancel_scheduled_deletion(account_repo, &mut account).await;
+
+    destroy_session(&pending.session_token).await;
+    Ok(issue_verified_session(pending.user_id).await)
+}
+
+async fn replace_recovery_codes(
+    recovery_repo: &impl RecoveryCodes,
+    account: &Account,
+) -> Vec<String> {
+    let codes = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = &token::generate()[..10];
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect::<Vec<String>>();
+    let code_hashes = codes.iter().map(|x| recovery_code_hash(x)).collect();
+    recovery_repo
+        .replace(account.id().unwrap(), code_hashes)
+        .await;
+    codes
+}
+
+fn recovery_code_hash(code: &str) -> String {
+    let normalized = code.trim().replace(['-', ' '], "").to_lowercase();
+    token::digest(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sha2::{Digest, Sha256};
+
+    use crate::entities::{Account, LoginAttempt};
+    use crate::repositories::{MockAccounts, MockLoginAttempts, MockRecoveryCodes};
+    use crate::request::{PendingUserContext, UserContext};
+    use crate::services::SessionError;
+    use crate::totp;
+
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn account(id: i32) -> Account {
+        Account::new(
+            id,
+            format!("{}@example.com", id),
+            format!("{:x}", Sha256::digest(format!("password{}", id).as_bytes())),
+            format!("display_name{}", id),
+        )
+    }
+
+    fn current_code() -> String {
+        totp::code(SECRET, Utc::now().timestamp() / 30).unwrap()
+    }
+
+    fn enrolling_account(id: i32) -> Account {
+        let mut account = account(id);
+        account.totp_pending_secret = Some(SECRET.to_string());
+        account
+    }
+
+    #[tokio::test]
+    async fn test_two_factor_settings() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| e.totp_pending_secret.is_some() && e.totp_secret.is_none())
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::two_factor_settings(&accounts, &user_context).await;
+        assert!(!result.enabled);
+        assert!(!result.secret.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_two_factor_settings_pending() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(enrolling_account(id)));
+        accounts.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::two_factor_settings(&accounts, &user_context).await;
+        assert_eq!(result.secret, SECRET);
+    }
+
+    #[tokio::test]
+    async fn test_enable_two_factor() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(enrolling_account(id)));
+        accounts
+            .expect_store()
+            .withf(|e| {
+                e.totp_secret.as_deref() == Some(SECRET)
+                    && e.totp_pending_secret.is_none()
+                    && e.totp_last_step.is_some()
+            })
+            .once()
+            .return_const(());
+
+        let mut recovery_codes = MockRecoveryCodes::new();
+        recovery_codes
+            .expect_replace()
+            .withf(|id, hashes| *id == 1 && hashes.len() == 10)
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::enable_two_factor(
+            &accounts,
+            &recovery_codes,
+            &user_context,
+            &current_code(),
+            "password1",
+        )
+        .await;
+        assert_eq!(result.map(|x| x.len()), Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_enable_two_factor_wrong_code() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(enrolling_account(id)));
+        accounts.expect_store().never();
+
+        let mut recovery_codes = MockRecoveryCodes::new();
+        recovery_codes.expect_replace().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::enable_two_factor(
+            &accounts,
+            &recovery_codes,
+            &user_context,
+            "000000x",
+            "password1",
+        )
+        .await;
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_enable_two_factor_not_enrolling() {
+        let mut accounts = MockAccounts::new();
+        accounts
+            .expect_find_by_id()
+            .returning(|id| Some(account(id)));
+        accounts.expect_store().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result = super::enable_two_factor(
+            &accounts,
+            &MockRecoveryCodes::new(),
+            &user_context,
+            &current_code(),
+            "password1",
+        )
+        .await;
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reenroll_two_factor() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|id| {
+            let mut account = account(id);
+            account.totp_secret = Some(SECRET.to_string());
+            Some(account)
+        });
+        accounts
+            .expect_store()
+            .withf(|e| {
+                e.totp_secret.as_deref() == Some(SECRET)
+                    && e.totp_pending_secret.as_deref().map(|x| x != SECRET) == Some(true)
+            })
+            .once()
+            .return_const(());
+
+        let user_context = UserContext { user_id: 1 };
+        assert!(super::reenroll_two_factor(&accounts, &user_context, "password1").await);
+        assert!(!super::reenroll_two_factor(&accounts, &user_context, "password2").await);
+    }
+
+    #[tokio::test]
+    async fn test_disable_two_factor_wrong_password() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|id| {
+            let mut account = account(id);
+            account.totp_secret = Some(SECRET.to_string());
+            Some(account)
+        });
+        accounts.expect_store().never();
+
+        let mut recovery_codes = MockRecoveryCodes::new();
+        recovery_codes.expect_replace().never();
+
+        let user_context = UserContext { user_id: 1 };
+        let result =
+            super::disable_two_factor(&accounts, &recovery_codes, &user_context, "password2").await;
+        assert!(!result);
+    }
+
+    #[tokio::test]
+    async fn test_complete_two_factor_invalid() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().returning(|id| {
+            let mut account = account(id);
+            account.totp_secret = Some(SECRET.to_string());
+            Some(account)
+        });
+
+        let mut recovery_codes = MockRecoveryCodes::new();
+        recovery_codes.expect_consume().returning(|_, _| false);
+
+        let mut attempts = MockLoginAttempts::new();
+        attempts.expect_find_by().returning(|_| None);
+        attempts
+            .expect_record_failure()
+            .withf(|key, _| key == "two_factor:1")
+            .once()
+            .return_const(());
+
+        let pending = PendingUserContext {
+            user_id: 1,
+            session_token: "token".to_string(),
+        };
+        let result = super::complete_two_factor(
+            &accounts,
+            &recovery_codes,
+            &attempts,
+            &pending,
+            "abcde-12345",
+        )
+        .await;
+        assert_eq!(result.err(), Some(SessionError::Invalid));
+    }
+
+    #[tokio::test]
+    async fn test_complete_two_factor_too_many_attempts() {
+        let mut accounts = MockAccounts::new();
+        accounts.expect_find_by_id().never();
+
+        let mut attempts = MockLoginAttempts::new();
+        attempts
+            .expect_find_by()
+            .returning(|key| Some(LoginAttempt::new(key.to_string(), 10, Utc::now())));
+
+        let pending = PendingUserContext {
+            user_id: 1,
+            session_token: "token".to_string(),
+        };
+        let result = super::complete_two_factor(
+            &accounts,
+            &MockRecoveryCodes::new(),
+            &attempts,
+            &pending,
+            &current_code(),
+        )
+        .await;
+        assert_eq!(result.err(), Some(SessionError::TooManyAttempts));
+    }
+}
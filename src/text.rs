@@ -0,0 +1,613 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::entities::Account;
+
+#[derive(Debug, PartialEq)]
+pub enum Token<'a> {
+    Text(&'a str),
+    /// An `@handle`, without the `@`.
+    Mention(&'a str),
+    /// A `#tag`, without the `#`.
+    Hashtag(&'a str),
+    /// An `http://` or `https://` URL.
+    Url(&'a str),
+}
+
+/// Splits a tweet message into plain text, `@handle` mentions, `#tag` hashtags and URLs.
+/// A marker right after a letter or digit, as in an email address, doesn't start either,
+/// and nothing inside a URL does.
+pub fn tokenize(message: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut prev: Option<char> = None;
+    let mut chars = message.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let start = i + c.len_utf8();
+        let after_word = prev.map(|x| x.is_alphanumeric()).unwrap_or(false);
+        let (token, end) = if let Some(end) = url_end(message, i).filter(|_| !after_word) {
+            while chars.peek().map(|x| x.0 < end).unwrap_or(false) {
+                chars.next();
+            }
+            (Some(Token::Url(&message[i..end])), end)
+        } else if c == '@' && !prev.map(is_handle_char).unwrap_or(false) {
+            let end = scan(&mut chars, start, is_handle_char);
+            let handle = &message[start..end];
+            let token = Some(Token::Mention(handle)).filter(|_| Account::is_valid_handle(handle));
+            (token, end)
+        } else if (c == '#' || c == '＃') && !prev.map(is_tag_char).unwrap_or(false) {
+            let end = scan(&mut chars, start, is_tag_char);
+            let tag = &message[start..end];
+            (Some(Token::Hashtag(tag)).filter(|_| is_valid_tag(tag)), end)
+        } else {
+            prev = Some(c);
+            continue;
+        };
+
+        if let Some(token) = token {
+            if text_start < i {
+                tokens.push(Token::Text(&message[text_start..i]));
+            }
+            tokens.push(token);
+            text_start = end;
+        }
+        prev = message[..end].chars().last();
+    }
+    if text_start < message.len() {
+        tokens.push(Token::Text(&message[text_start..]));
+    }
+    tokens
+}
+
+/// Inline Markdown formatting.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    pub strong: bool,
+    pub emphasis: bool,
+    pub code: bool,
+}
+
+/// A paragraph, or a run of lines quoted with `> `.
+#[derive(Debug, PartialEq)]
+pub struct Block<'a> {
+    pub quote: bool,
+    pub lines: Vec<&'a str>,
+}
+
+/// Groups the lines of a message into paragraphs and quotes. Blank lines separate paragraphs.
+pub fn blocks(message: &str) -> Vec<Block<'_>> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut continues = false;
+    for line in message.lines() {
+        let (quote, line) = match line.strip_prefix("> ") {
+            Some(x) => (true, x),
+            None if line.trim_end() == ">" => (true, ""),
+            None => (false, line),
+        };
+        if !quote && line.trim().is_empty() {
+            continues = false;
+            continue;
+        }
+        match blocks.last_mut() {
+            Some(block) if continues && block.quote == quote => block.lines.push(line),
+            _ => blocks.push(Block {
+                quote,
+                lines: vec![line],
+            }),
+        }
+        continues = true;
+    }
+    blocks
+}
+
+/// Tokenizes one line and applies the Markdown subset: `**strong**`, `*emphasis*` or
+/// `_emphasis_`, and `` `code` ``. Code is shown as written, markers inside URLs, handles and
+/// tags are left alone, and markers without a partner stay as they are.
+pub fn format(line: &str) -> Vec<(Token<'_>, Style)> {
+    let mut items = Vec::new();
+    let mut rest = line;
+    while !rest.is_empty() {
+        let code = rest.find('`').and_then(|start| {
+            let len = rest[start + 1..].find('`')?;
+            Some((start, start + 1 + len))
+        });
+        let plain_end = code.map(|x| x.0).unwrap_or(rest.len());
+        for token in tokenize(&rest[..plain_end]) {
+            match token {
+                Token::Text(text) => delimiters(line, text, &mut items),
+                token => items.push(Item::Token(token)),
+            }
+        }
+        match code {
+            Some((start, end)) => {
+                items.push(Item::Code(&rest[start + 1..end]));
+                rest = &rest[end + 1..];
+            }
+            None => rest = "",
+        }
+    }
+
+    // Pairs each closing marker with the nearest opening one of the same kind.
+    let mut matched = vec![false; items.len()];
+    let mut openers: Vec<usize> = Vec::new();
+    for i in 0..items.len() {
+        if let Item::Delimiter(text, can_open, can_close) = items[i] {
+            let opener = openers
+                .iter()
+                .rposition(|x| matches!(items[*x], Item::Delimiter(y, ..) if y == text));
+            match opener {
+                Some(position) if can_close => {
+                    matched[openers[position]] = true;
+                    matched[i] = true;
+                    openers.truncate(position);
+                }
+                _ if can_open => openers.push(i),
+                _ => {}
+            }
+        }
+    }
+
+    let mut formatted = Vec::new();
+    let (mut strong, mut emphasis) = (false, false);
+    for (i, item) in items.into_iter().enumerate() {
+        let style = Style {
+            strong,
+            emphasis,
+            code: false,
+        };
+        match item {
+            Item::Token(token) => formatted.push((token, style)),
+            Item::Code(text) => formatted.push((
+                Token::Text(text),
+                Style {
+                    code: true,
+                    ..style
+                },
+            )),
+            Item::Delimiter(text, ..) if matched[i] => {
+                if text == "**" {
+                    strong = !strong;
+                } else {
+                    emphasis = !emphasis;
+                }
+            }
+            Item::Delimiter(text, ..) => formatted.push((Token::Text(text), style)),
+        }
+    }
+    formatted
+}
+
+enum Item<'a> {
+    Token(Token<'a>),
+    Code(&'a str),
+    /// A `**`, `*` or `_` marker, with whether it can open and close a span.
+    Delimiter(&'a str, bool, bool),
+}
+
+/// Splits a piece of text taken from `line` around its emphasis markers. Whether a marker can
+/// open or close depends on the characters around it in the whole line; `_` also has to be
+/// at a word boundary, so `snake_case_name` is left alone.
+fn delimiters<'a>(line: &str, text: &'a str, items: &mut Vec<Item<'a>>) {
+    let offset = text.as_ptr() as usize - line.as_ptr() as usize;
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let len = if text[i..].starts_with("**") {
+            2
+        } else if text[i..].starts_with('*') || text[i..].starts_with('_') {
+            1
+        } else {
+            i += text[i..].chars().next().unwrap().len_utf8();
+            continue;
+        };
+        let before = line[..offset + i].chars().last();
+        let after = line[offset + i + len..].chars().next();
+        let word = |x: Option<char>| x.map(|x| x.is_alphanumeric()).unwrap_or(false);
+        let underscore = text[i..].starts_with('_');
+        let can_open =
+            after.map(|x| !x.is_whitespace()).unwrap_or(false) && !(underscore && word(before));
+        let can_close =
+            before.map(|x| !x.is_whitespace()).unwrap_or(false) && !(underscore && word(after));
+        if start < i {
+            items.push(Item::Token(Token::Text(&text[start..i])));
+        }
+        items.push(Item::Delimiter(&text[i..i + len], can_open, can_close));
+        i += len;
+        start = i;
+    }
+    if start < text.len() {
+        items.push(Item::Token(Token::Text(&text[start..])));
+    }
+}
+
+/// The distinct handles mentioned in the message, lowercased, in order of appearance.
+pub fn mentions(message: &str) -> Vec<String> {
+    let mut handles: Vec<String> = Vec::new();
+    for token in tokenize(message) {
+        if let Token::Mention(handle) = token {
+            let handle = handle.to_lowercase();
+            if !handles.contains(&handle) {
+                handles.push(handle);
+            }
+        }
+    }
+    handles
+}
+
+/// The distinct hashtags in the message, normalized with `normalize`.
+pub fn hashtags(message: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for token in tokenize(message) {
+        if let Token::Hashtag(tag) = token {
+            let tag = normalize(tag);
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+/// Folds full-width and half-width forms together (NFKC) and ignores case,
+/// so that `#Rust`, `#ｒｕｓｔ` and `#rust` are the same tag.
+pub fn normalize(text: &str) -> String {
+    text.nfkc().collect::<String>().to_lowercase()
+}
+
+/// The distinct URLs in the message, in order of appearance.
+pub fn urls(message: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for token in tokenize(message) {
+        if let Token::Url(url) = token {
+            if !urls.iter().any(|x| x == url) {
+                urls.push(url.to_string());
+            }
+        }
+    }
+    urls
+}
+
+/// Splits the text after each sentence, keeping the whitespace that follows it, so the pieces
+/// join back into the text. A sentence ends at `。！？!?`, at `.` before whitespace, or at a line
+/// break, along with any closing brackets or quotes right after.
+pub fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let ends = match c {
+            '。' | '！' | '？' | '!' | '?' | '\n' => true,
+            '.' => chars.peek().map(|x| x.1.is_whitespace()).unwrap_or(true),
+            _ => false,
+        };
+        if !ends {
+            continue;
+        }
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, next)) = chars.peek() {
+            if !is_sentence_tail(next) {
+                break;
+            }
+            end = j + next.len_utf8();
+            chars.next();
+        }
+        sentences.push(&text[start..end]);
+        start = end;
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+    sentences
+}
+
+/// Splits `text` into pieces, flagging the ones that match one of the terms.
+/// Matching ignores ASCII case, like the search itself does for Latin text.
+pub fn highlight<'a>(text: &'a str, terms: &[String]) -> Vec<(&'a str, bool)> {
+    let mut pieces = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let matched = terms
+            .iter()
+            .filter(|x| !x.is_empty())
+            .filter(|x| {
+                text.get(i..i + x.len())
+                    .map(|y| y.eq_ignore_ascii_case(x))
+                    .unwrap_or(false)
+            })
+            .map(|x| x.len())
+            .max();
+        match matched {
+            Some(len) => {
+                if plain_start < i {
+                    pieces.push((&text[plain_start..i], false));
+                }
+                pieces.push((&text[i..i + len], true));
+                i += len;
+                plain_start = i;
+            }
+            None => i += text[i..].chars().next().unwrap().len_utf8(),
+        }
+    }
+    if plain_start < text.len() {
+        pieces.push((&text[plain_start..], false));
+    }
+    pieces
+}
+
+fn scan(chars: &mut Peekable<CharIndices>, start: usize, f: fn(char) -> bool) -> usize {
+    let mut end = start;
+    while let Some(&(i, c)) = chars.peek() {
+        if !f(c) {
+            break;
+        }
+        end = i + c.len_utf8();
+        chars.next();
+    }
+    end
+}
+
+/// Where the URL starting at byte `i` ends, if one starts there. It runs until whitespace or
+/// a non-ASCII character, without trailing punctuation, emphasis markers or an unmatched
+/// closing bracket, so `(https://example.com/a_(b)).` and `https://example.com/です` end
+/// where a reader expects.
+fn url_end(message: &str, i: usize) -> Option<usize> {
+    let rest = &message[i..];
+    let scheme = ["https://", "http://"].iter().find(|x| {
+        rest.get(..x.len())
+            .map(|y| y.eq_ignore_ascii_case(x))
+            .unwrap_or(false)
+    })?;
+    let mut end = rest
+        .find(|c: char| !c.is_ascii_graphic() || c == '<' || c == '>' || c == '"')
+        .unwrap_or(rest.len());
+    loop {
+        let url = &rest[..end];
+        match url.chars().last() {
+            Some('.' | ',' | ':' | ';' | '!' | '?' | '\'' | '*' | '_') => end -= 1,
+            Some(')') if url.matches('(').count() < url.matches(')').count() => end -= 1,
+            _ => break,
+        }
+    }
+    let host_start = rest[scheme.len()..end].chars().next()?;
+    if !host_start.is_ascii_alphanumeric() && host_start != '[' {
+        return None;
+    }
+    Some(i + end)
+}
+
+fn is_sentence_tail(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            c,
+            '。' | '！'
+                | '？'
+                | '!'
+                | '?'
+                | '.'
+                | '」'
+                | '』'
+                | '）'
+                | ')'
+                | '"'
+                | '\''
+                | '”'
+                | '’'
+        )
+}
+
+fn is_handle_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Any letter or digit, which covers kana, kanji and the prolonged sound mark.
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A tag made only of digits, like `#1`, is not a hashtag.
+fn is_valid_tag(tag: &str) -> bool {
+    tag.chars().any(|x| !x.is_numeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Block, Style, Token};
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            super::tokenize("こんにちは@Taro さん、mail@example.com"),
+            vec![
+                Token::Text("こんにちは"),
+                Token::Mention("Taro"),
+                Token::Text(" さん、mail@example.com"),
+            ]
+        );
+        assert_eq!(
+            super::tokenize("@a_very_long_handle_name @"),
+            vec![Token::Text("@a_very_long_handle_name @")]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_hashtags() {
+        assert_eq!(
+            super::tokenize("#ラーメン 美味しい＃東京 #1 a#b"),
+            vec![
+                Token::Hashtag("ラーメン"),
+                Token::Text(" 美味しい＃東京 #1 a#b"),
+            ]
+        );
+        assert_eq!(
+            super::tokenize("今日は＃東京。"),
+            vec![Token::Text("今日は＃東京。")]
+        );
+        assert_eq!(
+            super::tokenize("「＃東京」"),
+            vec![Token::Text("「"), Token::Hashtag("東京"), Token::Text("」"),]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_urls() {
+        assert_eq!(
+            super::tokenize("見て(https://example.com/a_(b)?q=1#top). @taro"),
+            vec![
+                Token::Text("見て("),
+                Token::Url("https://example.com/a_(b)?q=1#top"),
+                Token::Text("). "),
+                Token::Mention("taro"),
+            ]
+        );
+        assert_eq!(
+            super::tokenize("http://example.comです xhttp://example.com https://"),
+            vec![
+                Token::Url("http://example.com"),
+                Token::Text("です xhttp://example.com https://"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            super::blocks("一行目\r\n二行目\n\n\n> 引用\n>\n> 続き\n後"),
+            vec![
+                Block {
+                    quote: false,
+                    lines: vec!["一行目", "二行目"]
+                },
+                Block {
+                    quote: true,
+                    lines: vec!["引用", "", "続き"]
+                },
+                Block {
+                    quote: false,
+                    lines: vec!["後"]
+                },
+            ]
+        );
+        assert_eq!(
+            super::blocks(">_< \n"),
+            vec![Block {
+                quote: false,
+                lines: vec![">_< "]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let plain = Style::default();
+        let strong = Style {
+            strong: true,
+            ..plain
+        };
+        let emphasis = Style {
+            emphasis: true,
+            ..plain
+        };
+        let code = Style {
+            code: true,
+            ..plain
+        };
+        assert_eq!(
+            super::format("**太字** と *@taro* と `*code* @x`"),
+            vec![
+                (Token::Text("太字"), strong),
+                (Token::Text(" と "), plain),
+                (Token::Mention("taro"), emphasis),
+                (Token::Text(" と "), plain),
+                (Token::Text("*code* @x"), code),
+            ]
+        );
+        assert_eq!(
+            super::format("snake_case_name 2 * 3 _a *b"),
+            vec![
+                (Token::Text("snake"), plain),
+                (Token::Text("_"), plain),
+                (Token::Text("case"), plain),
+                (Token::Text("_"), plain),
+                (Token::Text("name 2 "), plain),
+                (Token::Text("*"), plain),
+                (Token::Text(" 3 "), plain),
+                (Token::Text("_"), plain),
+                (Token::Text("a "), plain),
+                (Token::Text("*"), plain),
+                (Token::Text("b"), plain),
+            ]
+        );
+        assert_eq!(
+            super::format("_https://example.com/a_b_ `x"),
+            vec![
+                (Token::Url("https://example.com/a_b"), emphasis),
+                (Token::Text(" `x"), plain),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlight() {
+        let terms = vec!["rust".to_string(), "東京".to_string()];
+        assert_eq!(
+            super::highlight("東京でRustを書く", &terms),
+            vec![
+                ("東京", true),
+                ("で", false),
+                ("Rust", true),
+                ("を書く", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mentions() {
+        assert_eq!(
+            super::mentions("@taro @Hanako @TARO"),
+            vec!["taro".to_string(), "hanako".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_hashtags() {
+        assert_eq!(
+            super::hashtags("#Rust #ｒｕｓｔ #ﾗｰﾒﾝ"),
+            vec!["rust".to_string(), "ラーメン".to_string()]
+        );
+        assert!(super::hashtags("https://example.com/#top").is_empty());
+    }
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(
+            super::urls("https://example.com, https://example.com and http://example.org/."),
+            vec![
+                "https://example.com".to_string(),
+                "http://example.org/".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sentences() {
+        assert_eq!(
+            super::sentences("今日は晴れ。「本当？」と聞かれた！ Really?! Yes. v1.2 is out.\nNext"),
+            vec![
+                "今日は晴れ。",
+                "「本当？」",
+                "と聞かれた！ ",
+                "Really?! ",
+                "Yes. ",
+                "v1.2 is out.\n",
+                "Next"
+            ]
+        );
+        assert_eq!(
+            super::sentences("See https://example.com/a.b. Done"),
+            vec!["See https://example.com/a.b. ", "Done"]
+        );
+        assert!(super::sentences("").is_empty());
+    }
+}
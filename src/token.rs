@@ -0,0 +1,39 @@
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Tests sign with a fixed key instead of reading the environment, which they share.
+fn secret_key() -> String {
+    if cfg!(test) {
+        "test-secret".to_string()
+    } else {
+        crate::constants::secret_key()
+    }
+}
+
+pub fn sign(payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret_key().as_bytes()).unwrap();
+    mac.update(payload.as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+pub fn verify(payload: &str, signature: &str) -> bool {
+    let signature = match hex::decode(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    let mut mac = HmacSha256::new_from_slice(secret_key().as_bytes()).unwrap();
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// A random token to hand out by email. Only its `digest` should be stored.
+pub fn generate() -> String {
+    let bytes: [u8; 32] = rand::random();
+    hex::encode(bytes)
+}
+
+pub fn digest(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
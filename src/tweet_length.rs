@@ -0,0 +1,96 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::text::{self, Token};
+
+/// The longest a tweet can be, in weighted characters.
+pub const MAX_LENGTH: usize = 280;
+/// Every URL counts this much, however long it is, as if it had been shortened.
+const URL_LENGTH: usize = 23;
+
+/// Counts the message the way Twitter does, after NFC normalization: URLs count 23, Latin
+/// and other alphabets count 1 per character, and CJK and everything else count 2. An emoji
+/// counts 2 as a whole, even when it is a sequence of code points such as a flag, a keycap,
+/// a skin tone or a ZWJ family.
+pub fn weighted_length(message: &str) -> usize {
+    let message = message.nfc().collect::<String>();
+    let mut length = 0;
+    let mut start = 0;
+    for token in text::tokenize(&message) {
+        if let Token::Url(url) = token {
+            let offset = url.as_ptr() as usize - message.as_ptr() as usize;
+            length += text_length(&message[start..offset]) + URL_LENGTH;
+            start = offset + url.len();
+        }
+    }
+    length + text_length(&message[start..])
+}
+
+fn text_length(text: &str) -> usize {
+    text.graphemes(true)
+        .map(|x| {
+            if is_emoji_sequence(x) {
+                2
+            } else {
+                x.chars().map(weight).sum()
+            }
+        })
+        .sum()
+}
+
+/// A cluster of several code points held together by a joiner, a variation selector, a
+/// keycap, skin tone or tag modifier, or made of regional indicators.
+fn is_emoji_sequence(grapheme: &str) -> bool {
+    grapheme.chars().nth(1).is_some()
+        && grapheme.chars().any(|x| {
+            matches!(x,
+                '\u{200D}' | '\u{FE0F}' | '\u{20E3}'
+                | '\u{1F1E6}'..='\u{1F1FF}'
+                | '\u{1F3FB}'..='\u{1F3FF}'
+                | '\u{E0020}'..='\u{E007F}')
+        })
+}
+
+/// The ranges Twitter weighs as 1: up to U+10FF, which covers Latin, Greek, Cyrillic, Arabic,
+/// Thai and so on, and some general punctuation such as dashes and quotes.
+fn weight(c: char) -> usize {
+    match c as u32 {
+        0..=0x10FF | 0x2000..=0x200D | 0x2010..=0x201F | 0x2032..=0x2037 => 1,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::weighted_length;
+
+    #[test]
+    fn test_weighted_length() {
+        assert_eq!(weighted_length(""), 0);
+        assert_eq!(weighted_length("Hello, world"), 12);
+        assert_eq!(weighted_length("こんにちは"), 10);
+        assert_eq!(weighted_length("ｱｲｳ ＡＢＣ"), 13);
+        assert_eq!(weighted_length("café “quoted” — ok"), 18);
+        // "e" followed by a combining acute accent is normalized to one character.
+        assert_eq!(weighted_length("cafe\u{301}"), 4);
+    }
+
+    #[test]
+    fn test_weighted_length_urls() {
+        assert_eq!(
+            weighted_length("見て https://example.com/a/very/long/path?with=query"),
+            4 + 1 + 23
+        );
+        assert_eq!(weighted_length("https://a.jp https://a.jp"), 23 + 1 + 23);
+    }
+
+    #[test]
+    fn test_weighted_length_emoji() {
+        assert_eq!(weighted_length("😀"), 2);
+        assert_eq!(weighted_length("👍🏽"), 2);
+        assert_eq!(weighted_length("👨‍👩‍👧‍👦"), 2);
+        assert_eq!(weighted_length("🇯🇵🇺🇸"), 4);
+        assert_eq!(weighted_length("1️⃣"), 2);
+        assert_eq!(weighted_length("❤️ok"), 4);
+    }
+}
@@ -0,0 +1,8 @@
+use askama::Template;
+
+/// Shown at the profile URL of an account that has been deleted.
+#[derive(Template)]
+#[template(path = "account_deleted.html")]
+pub struct AccountDeleted {
+    pub handle: String,
+}
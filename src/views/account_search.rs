@@ -0,0 +1,16 @@
+use askama::Template;
+use serde::Serialize;
+
+#[derive(Template)]
+#[template(path = "account_search.html")]
+pub struct AccountSearch {
+    pub query: String,
+    pub accounts: Vec<AccountSuggestion>,
+}
+
+/// Also what the typeahead endpoint returns as JSON.
+#[derive(Serialize)]
+pub struct AccountSuggestion {
+    pub handle: String,
+    pub display_name: String,
+}
@@ -0,0 +1,24 @@
+use askama::Template;
+use serde::Serialize;
+
+#[derive(Template)]
+#[template(path = "drafts.html")]
+pub struct DraftList {
+    /// The most recently updated first.
+    pub drafts: Vec<DraftItem>,
+    pub error: String,
+}
+
+pub struct DraftItem {
+    pub id: i32,
+    pub message: String,
+    /// Empty for none.
+    pub content_warning: String,
+    pub updated_at: String,
+}
+
+/// What the autosave endpoint returns, so the composer keeps saving to the same draft.
+#[derive(Serialize)]
+pub struct DraftSaved {
+    pub id: Option<i32>,
+}
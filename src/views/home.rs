@@ -1,9 +1,21 @@
 use askama::Template;
 
 use crate::views::partial::Tweet;
+use crate::views::DraftItem;
 
 #[derive(Template)]
 #[template(path = "home.html")]
 pub struct Home {
     pub tweets: Vec<Tweet>,
+    pub error: String,
+    pub notice: String,
+    /// Being resumed in the composer.
+    pub draft: Option<DraftItem>,
+    /// The tweet just posted, while it can still be taken back.
+    pub pending: Option<PendingTweet>,
+}
+
+pub struct PendingTweet {
+    pub id: i32,
+    pub seconds_left: i64,
 }
@@ -0,0 +1,7 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "login_link.html")]
+pub struct LoginLink {
+    pub sent: bool,
+}
@@ -0,0 +1,9 @@
+use askama::Template;
+
+use crate::views::partial::Tweet;
+
+#[derive(Template)]
+#[template(path = "mentions.html")]
+pub struct Mentions {
+    pub tweets: Vec<Tweet>,
+}
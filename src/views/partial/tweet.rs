@@ -1,19 +1,171 @@
+use std::collections::HashMap;
+use url::Url;
+
 use crate::entities::{Account, Tweet as TweetEntity};
+use crate::text::{self, Style, Token};
 
 pub struct Tweet {
     pub id: String,
     pub name: String,
-    pub message: String,
+    pub handle: String,
+    /// The message, images and card are collapsed behind it unless `expanded`.
+    pub content_warning: Option<String>,
+    pub expanded: bool,
+    pub blocks: Vec<Block>,
+    pub media: Vec<MediaItem>,
+    pub card: Option<Card>,
     pub posted_at: String,
+    /// Replies to another tweet of a thread.
+    pub in_thread: bool,
+    pub edited: bool,
+    /// Set for the viewer's own tweets while they can still be edited.
+    pub editable: bool,
+    /// Posted by the viewer, who can pin it to their profile.
+    pub own: bool,
+    pub pinned: bool,
+}
+
+pub struct MediaItem {
+    pub url: String,
+    pub thumbnail_url: String,
+    pub alt_text: String,
+}
+
+/// The preview of a page linked from the tweet.
+pub struct Card {
+    pub url: String,
+    pub domain: String,
+    pub title: String,
+    pub description: String,
+    pub image_url: Option<String>,
+}
+
+/// A paragraph or a quote of the message, line by line.
+pub struct Block {
+    pub quote: bool,
+    pub lines: Vec<Vec<Segment>>,
+}
+
+/// A piece of a line. Hashtags, URLs and mentions of existing accounts are linked; `external`
+/// links open in a new tab, and tell search engines not to vouch for them.
+pub struct Segment {
+    pub text: String,
+    pub href: Option<String>,
+    pub external: bool,
+    pub style: Style,
+    pub highlighted: bool,
 }
 
-impl From<(TweetEntity, &Account)> for Tweet {
-    fn from(e: (TweetEntity, &Account)) -> Self {
+impl Tweet {
+    /// Marks where the search terms appear in the message.
+    pub fn highlight(&mut self, terms: &[String]) {
+        for line in self.blocks.iter_mut().flat_map(|x| x.lines.iter_mut()) {
+            for segment in std::mem::take(line) {
+                if segment.href.is_some() {
+                    let highlighted = text::highlight(&segment.text, terms).iter().any(|x| x.1);
+                    line.push(Segment {
+                        highlighted,
+                        ..segment
+                    });
+                    continue;
+                }
+                for (piece, highlighted) in text::highlight(&segment.text, terms) {
+                    line.push(Segment {
+                        text: piece.to_string(),
+                        href: None,
+                        external: false,
+                        style: segment.style,
+                        highlighted,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// The map holds the accounts mentioned in the tweet; mentions missing from it stay plain text.
+impl From<(TweetEntity, &Account, &HashMap<i32, Account>)> for Tweet {
+    fn from(e: (TweetEntity, &Account, &HashMap<i32, Account>)) -> Self {
+        let blocks = text::blocks(&e.0.message)
+            .into_iter()
+            .map(|x| Block {
+                quote: x.quote,
+                lines: x.lines.iter().map(|x| segments(x, &e.0, e.2)).collect(),
+            })
+            .collect();
+        let media =
+            e.0.media
+                .iter()
+                .map(|x| MediaItem {
+                    url: format!("/media/{}", x.key),
+                    thumbnail_url: format!("/media/{}", x.thumbnail_key),
+                    alt_text: x.alt_text.clone(),
+                })
+                .collect();
+        let card = e.0.link_preview.as_ref().map(|x| Card {
+            url: x.url.clone(),
+            domain: Url::parse(&x.url)
+                .ok()
+                .and_then(|x| x.host_str().map(|x| x.to_string()))
+                .unwrap_or_default(),
+            title: x.title.clone(),
+            description: x.description.clone(),
+            image_url: x.image_url.clone(),
+        });
         Tweet {
             id: e.0.id().unwrap_or(-1).to_string(),
             name: e.1.display_name.clone(),
-            message: e.0.message,
+            handle: e.1.handle.clone(),
+            content_warning: e.0.content_warning.clone(),
+            expanded: false,
+            blocks,
+            media,
+            card,
             posted_at: e.0.posted_at.format("%Y/%m/%d %H:%M").to_string(),
+            in_thread: e.0.in_reply_to.is_some(),
+            edited: e.0.edited_at.is_some(),
+            editable: false,
+            own: false,
+            pinned: false,
+        }
+    }
+}
+
+/// Formats one line of the message. The text is kept as it is and escaped by the template,
+/// so nothing in the message can turn into markup of its own.
+fn segments(line: &str, tweet: &TweetEntity, accounts: &HashMap<i32, Account>) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    for (token, style) in text::format(line) {
+        let (text, href, external) = match token {
+            Token::Text(x) => (x.to_string(), None, false),
+            Token::Url(x) => (x.to_string(), Some(x.to_string()), true),
+            Token::Mention(handle) => {
+                let account = tweet
+                    .mentions
+                    .iter()
+                    .find(|x| x.handle.eq_ignore_ascii_case(handle))
+                    .and_then(|x| accounts.get(&x.account_id))
+                    .filter(|x| !x.is_deleted());
+                let href = account.map(|x| format!("/@{}", x.handle));
+                (format!("@{}", handle), href, false)
+            }
+            Token::Hashtag(tag) => {
+                let href = format!("/tags/{}", urlencoding::encode(&text::normalize(tag)));
+                (format!("#{}", tag), Some(href), false)
+            }
+        };
+        match segments.last_mut() {
+            Some(last) if href.is_none() && last.href.is_none() && last.style == style => {
+                last.text.push_str(&text)
+            }
+            _ => segments.push(Segment {
+                text,
+                href,
+                external,
+                style,
+                highlighted: false,
+            }),
         }
     }
+    segments
 }
@@ -0,0 +1,8 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "password_forgot.html")]
+pub struct PasswordForgot {
+    pub sent: bool,
+    pub invalid: bool,
+}
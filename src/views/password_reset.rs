@@ -0,0 +1,7 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "password_reset.html")]
+pub struct PasswordReset {
+    pub token: String,
+}
@@ -0,0 +1,13 @@
+use askama::Template;
+
+use crate::views::partial::Tweet;
+
+#[derive(Template)]
+#[template(path = "profile.html")]
+pub struct Profile {
+    pub display_name: String,
+    pub handle: String,
+    /// Shown above the rest, which still include it.
+    pub pinned: Option<Tweet>,
+    pub tweets: Vec<Tweet>,
+}
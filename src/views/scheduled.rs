@@ -0,0 +1,27 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "scheduled.html")]
+pub struct Scheduled {
+    /// The next to be published first.
+    pub tweets: Vec<ScheduledTweet>,
+}
+
+pub struct ScheduledTweet {
+    pub id: i32,
+    pub message: String,
+    pub image_count: usize,
+    /// RFC 3339, for showing it in the viewer's time zone.
+    pub publish_at: String,
+    pub publish_at_utc: String,
+}
+
+#[derive(Template)]
+#[template(path = "scheduled_edit.html")]
+pub struct ScheduledEdit {
+    pub id: i32,
+    pub message: String,
+    /// In UTC, as the value of a `datetime-local` input.
+    pub publish_at: String,
+    pub error: String,
+}
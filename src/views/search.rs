@@ -0,0 +1,20 @@
+use askama::Template;
+
+use crate::views::partial::Tweet;
+
+#[derive(Template)]
+#[template(path = "search.html")]
+pub struct Search {
+    pub query: String,
+    pub recency: bool,
+    /// Whether the current query is already among the saved searches.
+    pub saved_query: bool,
+    pub saved: Vec<SavedSearchLink>,
+    pub tweets: Vec<Tweet>,
+}
+
+pub struct SavedSearchLink {
+    pub id: String,
+    pub query: String,
+    pub href: String,
+}
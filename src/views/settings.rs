@@ -0,0 +1,15 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "settings.html")]
+pub struct Settings {
+    pub display_name: String,
+    pub handle: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
+    pub undo_send_seconds: i32,
+    pub always_expand_content_warnings: bool,
+    pub notice: String,
+    pub error: String,
+}
@@ -4,4 +4,10 @@ use askama::Template;
 #[template(path = "sign_in.html")]
 pub struct SignIn {
     pub error: bool,
+    pub throttled: bool,
+    pub password_reset: bool,
+    pub login_link_invalid: bool,
+    pub login_link_enabled: bool,
+    pub account_deleted: bool,
+    pub account_deletion_scheduled: bool,
 }
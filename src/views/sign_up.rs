@@ -2,4 +2,6 @@ use askama::Template;
 
 #[derive(Template)]
 #[template(path = "sign_up.html")]
-pub struct SignUp;
+pub struct SignUp {
+    pub error: String,
+}
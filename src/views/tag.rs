@@ -0,0 +1,10 @@
+use askama::Template;
+
+use crate::views::partial::Tweet;
+
+#[derive(Template)]
+#[template(path = "tag.html")]
+pub struct Tag {
+    pub tag: String,
+    pub tweets: Vec<Tweet>,
+}
@@ -0,0 +1,20 @@
+use askama::Template;
+
+use crate::views::partial::Tweet;
+
+#[derive(Template)]
+#[template(path = "thread.html")]
+pub struct Thread {
+    /// In the order they were posted.
+    pub tweets: Vec<Tweet>,
+}
+
+#[derive(Template)]
+#[template(path = "thread_composer.html")]
+pub struct ThreadComposer {
+    /// What was written, kept when the thread couldn't be posted.
+    pub segments: Vec<String>,
+    pub numbered: bool,
+    pub content_warning: String,
+    pub error: String,
+}
@@ -0,0 +1,16 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "trash.html")]
+pub struct Trash {
+    /// Most recently deleted first.
+    pub tweets: Vec<DeletedTweet>,
+}
+
+pub struct DeletedTweet {
+    pub id: i32,
+    pub message: String,
+    pub image_count: usize,
+    pub deleted_at: String,
+    pub restorable_until: String,
+}
@@ -0,0 +1,9 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "tweet_edit.html")]
+pub struct TweetEdit {
+    pub id: i32,
+    pub message: String,
+    pub error: String,
+}
@@ -0,0 +1,16 @@
+use askama::Template;
+
+use crate::views::partial::Tweet;
+
+#[derive(Template)]
+#[template(path = "tweet_history.html")]
+pub struct TweetHistory {
+    pub tweet: Tweet,
+    /// Newest first.
+    pub revisions: Vec<Revision>,
+}
+
+pub struct Revision {
+    pub message: String,
+    pub written_at: String,
+}
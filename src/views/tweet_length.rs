@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+/// What the composer's counter gets as JSON. `remaining` goes negative when over the limit.
+#[derive(Serialize)]
+pub struct TweetLength {
+    pub length: usize,
+    pub remaining: i64,
+}
@@ -0,0 +1,8 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "two_factor_login.html")]
+pub struct TwoFactorLogin {
+    pub error: bool,
+    pub throttled: bool,
+}
@@ -0,0 +1,7 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "two_factor_recovery_codes.html")]
+pub struct TwoFactorRecoveryCodes {
+    pub codes: Vec<String>,
+}
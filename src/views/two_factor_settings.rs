@@ -0,0 +1,12 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "two_factor_settings.html")]
+pub struct TwoFactorSettings {
+    pub enabled: bool,
+    /// Enrolling another authenticator while the current one is still in use.
+    pub reenrolling: bool,
+    pub secret: String,
+    pub qr_code_svg: String,
+    pub error: bool,
+}
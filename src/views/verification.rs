@@ -0,0 +1,8 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "verification.html")]
+pub struct Verification {
+    pub sent: bool,
+    pub invalid: bool,
+}